@@ -1174,7 +1174,7 @@ bool display::Application::Initialize(uint32_t width, uint32_t height, std::stri
 
     // create instances from solved molecule
     std::string jsonFilePath = "/home/seb/projects/lewis-structure-visualizer/solver/out/" + moleculePath;
-    std::string csvFilePath = "/home/seb/projects/lewis-structure-visualizer/data/data.csv";
+    std::string csvFilePath = "/home/seb/projects/lewis-structure-visualizer/solver/data/data.csv";
     molecule::Molecule molecule(jsonFilePath, csvFilePath);
     auto bondedAtoms = molecule.ComputeAtomLocsRots().value();
 
@@ -4,7 +4,7 @@
 
 int main() {
     std::string jsonFilePath = "/home/seb/projects/lewis-structure-visualizer/solver/out/N2_0.json";
-    std::string csvFilePath = "/home/seb/projects/lewis-structure-visualizer/data/data.csv";
+    std::string csvFilePath = "/home/seb/projects/lewis-structure-visualizer/solver/data/data.csv";
 
     molecule::Molecule mol;
     mol.FillMoleculeFromJSON(jsonFilePath, csvFilePath);
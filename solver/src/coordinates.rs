@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+// covalent radius used for elements without one in the table, in picometres
+const DEFAULT_RADIUS: f64 = 75.0;
+
+type Vec3 = [f64; 3];
+
+fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn scale(a: Vec3, s: f64) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(a: Vec3) -> Vec3 {
+    let length = dot(a, a).sqrt();
+    if length < 1e-12 { a } else { scale(a, 1.0 / length) }
+}
+
+// rotates `v` by `angle` around the unit vector `axis` (Rodrigues' formula)
+fn rotate(v: Vec3, axis: Vec3, angle: f64) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    add(add(scale(v, cos), scale(cross(axis, v), sin)), scale(axis, dot(axis, v) * (1.0 - cos)))
+}
+
+// any unit vector perpendicular to `a`
+fn perpendicular(a: Vec3) -> Vec3 {
+    let other = if a[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+    normalize(cross(a, other))
+}
+
+// rotates every direction so that `from` ends up on `to` (both unit vectors)
+fn align(directions: &mut [Vec3], from: Vec3, to: Vec3) {
+    let axis = cross(from, to);
+    let (axis, angle) = if dot(axis, axis) < 1e-18 {
+        if dot(from, to) > 0.0 { return } else { (perpendicular(from), PI) }
+    } else {
+        (normalize(axis), dot(axis, axis).sqrt().atan2(dot(from, to)))
+    };
+    for direction in directions.iter_mut() {
+        *direction = rotate(*direction, axis, angle);
+    }
+}
+
+fn ring(count: usize, z: f64, start: f64) -> impl Iterator<Item = Vec3> {
+    let radius = (1.0 - z * z).sqrt();
+    (0..count).map(move |k| {
+        let angle = start + 2.0 * PI * k as f64 / count as f64;
+        [radius * angle.cos(), radius * angle.sin(), z]
+    })
+}
+
+// ideal directions of the electron domains for a steric number; lone pairs take the first ones, which are where
+// VSEPR puts them (equatorial for a trigonal bipyramid, trans for an octahedron, axial for a pentagonal bipyramid)
+fn domain_directions(steric_number: usize) -> Vec<Vec3> {
+    let s = 1.0 / 3f64.sqrt();
+    match steric_number {
+        0 => vec![],
+        1 => vec![[1.0, 0.0, 0.0]],
+        2 => vec![[1.0, 0.0, 0.0], [-1.0, 0.0, 0.0]],
+        3 => ring(3, 0.0, PI / 2.0).collect(),
+        4 => vec![[s, s, s], [s, -s, -s], [-s, s, -s], [-s, -s, s]],
+        5 => ring(3, 0.0, 0.0).chain([[0.0, 0.0, 1.0], [0.0, 0.0, -1.0]]).collect(),
+        6 => vec![[0.0, 0.0, 1.0], [0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]],
+        7 => [[0.0, 0.0, 1.0], [0.0, 0.0, -1.0]].into_iter().chain(ring(5, 0.0, 0.0)).collect(),
+        // square antiprism
+        8 => ring(4, 0.5, 0.0).chain(ring(4, -0.5, PI / 4.0)).collect(),
+        // tricapped trigonal prism
+        _ => ring(3, 0.0, 0.0).chain(ring(3, 0.6, PI / 3.0)).chain(ring(3, -0.6, PI / 3.0)).collect(),
+    }
+}
+
+// what the placement needs to know about an atom
+#[derive(Debug, Clone, PartialEq)]
+pub struct VseprAtom {
+    // covalent radius in picometres
+    pub radius: Option<f64>,
+    // sigma bonds and lone pairs around the atom
+    pub steric_number: usize,
+    pub hydrogen: bool,
+}
+
+// bond length in angstroms from the covalent radii, shortened for double and triple bonds
+fn bond_length(a: &VseprAtom, b: &VseprAtom, order: u32) -> f64 {
+    let single = (a.radius.unwrap_or(DEFAULT_RADIUS) + b.radius.unwrap_or(DEFAULT_RADIUS)) / 100.0;
+    match order {
+        2 => single * 0.87,
+        3 => single * 0.78,
+        _ => single,
+    }
+}
+
+// 3D coordinates in angstroms: a breadth first walk lays each atom's electron domains out in their VSEPR
+// directions, turned so that one bond points back at the atom it was reached from and the next one is anti to
+// the bond before that (so chains come out staggered and zigzag); heavier neighbors get the first free bonds,
+// bonds closing a ring stay wherever their atoms end up and disconnected pieces are set side by side
+pub fn vsepr_coordinates(atoms: &[VseprAtom], bonds: &[(usize, usize, u32)]) -> Vec<Vec3> {
+    let mut neighbors: Vec<Vec<(usize, u32)>> = vec![vec![]; atoms.len()];
+    for &(a, b, order) in bonds {
+        neighbors[a].push((b, order));
+        neighbors[b].push((a, order));
+    }
+    for list in neighbors.iter_mut() {
+        list.sort_by_key(|&(j, _)| (atoms[j].hydrogen, j));
+    }
+
+    let mut positions: Vec<Option<Vec3>> = vec![None; atoms.len()];
+    let mut reached_from: Vec<Option<usize>> = vec![None; atoms.len()];
+    let mut offset = 0.0;
+    for start in 0..atoms.len() {
+        if positions[start].is_some() {
+            continue;
+        }
+        positions[start] = Some([offset, 0.0, 0.0]);
+        let mut queue = VecDeque::from([start]);
+        let mut piece = vec![start];
+        while let Some(atom) = queue.pop_front() {
+            let parent = reached_from[atom];
+            let here = positions[atom].unwrap_or_default();
+            let bonded = neighbors[atom].len();
+            let steric_number = atoms[atom].steric_number.max(bonded);
+            let mut directions = domain_directions(steric_number);
+            // past nine domains there are no more directions, lone pairs that do not fit next to the bonds are left out
+            let lone_pairs = (steric_number - bonded).min(directions.len().saturating_sub(bonded));
+
+            if let Some(parent) = parent {
+                let back = normalize(add(positions[parent].unwrap_or_default(), scale(here, -1.0)));
+                let first_bond = directions[lone_pairs];
+                align(&mut directions, first_bond, back);
+
+                // turn around the bond back so the next bond is anti to the one before the parent
+                let grandparent = reached_from[parent];
+                if let (Some(grandparent), Some(&next)) = (grandparent, directions.get(lone_pairs + 1)) {
+                    let before = add(positions[grandparent].unwrap_or_default(), scale(positions[parent].unwrap_or_default(), -1.0));
+                    let flat = |v: Vec3| add(v, scale(back, -dot(v, back)));
+                    let (want, have) = (flat(scale(before, -1.0)), flat(next));
+                    if dot(want, want) > 1e-12 && dot(have, have) > 1e-12 {
+                        let (want, have) = (normalize(want), normalize(have));
+                        let angle = dot(cross(have, want), back).atan2(dot(have, want));
+                        for direction in directions.iter_mut() {
+                            *direction = rotate(*direction, back, angle);
+                        }
+                    }
+                }
+            }
+
+            // bonds take the domains after the lone pairs, the one back to the parent first
+            let mut free = directions.into_iter().skip(lone_pairs + usize::from(parent.is_some()));
+            for &(next, order) in neighbors[atom].iter() {
+                if Some(next) == parent || positions[next].is_some() {
+                    continue;
+                }
+                let Some(direction) = free.next() else { break };
+                let length = bond_length(&atoms[atom], &atoms[next], order);
+                positions[next] = Some(add(here, scale(direction, length)));
+                reached_from[next] = Some(atom);
+                queue.push_back(next);
+                piece.push(next);
+            }
+        }
+        let right = piece.iter().filter_map(|&i| positions[i]).map(|[x, _, _]| x).fold(offset, f64::max);
+        offset = right + 3.0;
+    }
+
+    positions.into_iter().map(Option::unwrap_or_default).collect()
+}
+
+// atoms in the plain .xyz format: count, comment line, then one `symbol x y z` line per atom in angstroms
+pub fn xyz_block(comment: &str, symbols: &[String], positions: &[Vec3]) -> String {
+    let mut block = format!("{}\n{}\n", symbols.len(), comment);
+    for (symbol, [x, y, z]) in symbols.iter().zip(positions) {
+        block.push_str(&format!("{:<2} {:>12.6} {:>12.6} {:>12.6}\n", symbol, x, y, z));
+    }
+    block
+}
@@ -0,0 +1,472 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::geometry::Geometry;
+
+// one atom of a Lewis diagram
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagramAtom {
+    // element symbol, padded to two characters
+    pub symbol: String,
+    // index among the atoms of the same element, as in the json output
+    pub id: u32,
+    // nonbonding electrons
+    pub lone: u32,
+    pub charge: i32,
+    pub geometry: Option<Geometry>,
+}
+
+// grid directions counterclockwise from east, so turning by 45 degrees is adding one
+const E: usize = 0;
+const NE: usize = 1;
+const N: usize = 2;
+const NW: usize = 3;
+const W: usize = 4;
+const SW: usize = 5;
+const S: usize = 6;
+const SE: usize = 7;
+
+// (row, column) step of each direction, rows growing downwards
+pub const STEPS: [(i32, i32); 8] = [(0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1), (1, 0), (1, 1)];
+
+// sides lone pairs go to, best first; the corners are only used once the sides are taken
+pub const SLOTS: [usize; 8] = [N, S, W, E, NE, SE, NW, SW];
+
+// whether a bond of a flat projection lies in the page or points out of it (a wedge) or into it (a dash)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Depth {
+    Plane,
+    Front,
+    Back,
+}
+
+// flat projection of each shape as the directions of its bonds, the ones for heavier neighbors first, with the
+// bonds that would point out of or into the page in a perspective drawing
+fn projection(geometry: Option<Geometry>) -> &'static [(usize, Depth)] {
+    use Depth::{Back, Front, Plane};
+    match geometry {
+        Some(Geometry::Linear2) => &[(E, Plane)],
+        Some(Geometry::Linear | Geometry::Linear3Lone) => &[(E, Plane), (W, Plane)],
+        Some(Geometry::Bent1Lone | Geometry::Bent2Lone) => &[(SE, Plane), (SW, Plane)],
+        Some(Geometry::TrigonalPlanar) => &[(E, Plane), (NW, Plane), (SW, Plane)],
+        Some(Geometry::TrigonalPyramidal) => &[(E, Plane), (W, Front), (S, Back)],
+        Some(Geometry::TShape) => &[(E, Plane), (N, Plane), (S, Plane)],
+        Some(Geometry::Tetrahedral) => &[(E, Plane), (W, Plane), (N, Front), (S, Back)],
+        Some(Geometry::SquarePlanar) => &[(E, Plane), (W, Plane), (N, Plane), (S, Plane)],
+        Some(Geometry::Seesaw) => &[(E, Plane), (W, Plane), (SE, Front), (SW, Back)],
+        Some(Geometry::TrigonalBipyramidal) => &[(E, Plane), (W, Back), (N, Plane), (S, Plane), (SE, Front)],
+        Some(Geometry::SquarePyramidal) => &[(N, Plane), (E, Back), (W, Back), (SE, Front), (SW, Front)],
+        Some(Geometry::PentagonalPlanar) => &[(N, Plane), (E, Plane), (W, Plane), (SE, Plane), (SW, Plane)],
+        Some(Geometry::Octahedral) => &[(E, Plane), (W, Plane), (N, Plane), (S, Plane), (NE, Back), (SW, Front)],
+        Some(Geometry::PentagonalPyramidal) => &[(N, Plane), (E, Plane), (W, Plane), (SE, Plane), (SW, Plane), (S, Plane)],
+        Some(Geometry::PentagonalBipyramidal) => &[(E, Plane), (W, Plane), (N, Plane), (S, Plane), (NE, Plane), (SW, Plane), (SE, Plane)],
+        _ => &[(E, Plane), (W, Plane), (N, Plane), (S, Plane), (NE, Plane), (SW, Plane), (SE, Plane), (NW, Plane)],
+    }
+}
+
+fn step((row, column): (i32, i32), direction: usize, distance: i32) -> (i32, i32) {
+    let (dr, dc) = STEPS[direction];
+    (row + dr * distance, column + dc * distance)
+}
+
+// direction from one cell to a neighboring one
+pub fn direction_between(from: (i32, i32), to: (i32, i32)) -> Option<usize> {
+    (0..8).find(|&d| step(from, d, 1) == to)
+}
+
+// (row, column) of a grid cell
+type Cell = (i32, i32);
+
+// walks around a ring of 3 to 8 atoms on the grid, each step to a neighboring cell and the last one back to the
+// start, as round as the grid allows (a hexagon for 6)
+fn ring_steps(size: usize) -> Option<&'static [usize]> {
+    match size {
+        3 => Some(&[E, SW, N]),
+        4 => Some(&[E, S, W, N]),
+        5 => Some(&[E, S, SW, NW, NE]),
+        6 => Some(&[E, SE, SW, W, NW, NE]),
+        7 => Some(&[E, E, SE, SW, W, NW, N]),
+        8 => Some(&[E, E, S, S, W, W, N, N]),
+        _ => None,
+    }
+}
+
+// the shortest ring through the bond from `atom` to `next`, starting with them, from the shortest path back
+// from `next` to `atom` that does not take the bond itself
+fn smallest_ring(neighbors: &[Vec<usize>], atom: usize, next: usize) -> Option<Vec<usize>> {
+    let mut reached_from = vec![None; neighbors.len()];
+    reached_from[next] = Some(next);
+    let mut queue = VecDeque::from([next]);
+    while let Some(current) = queue.pop_front() {
+        for &other in neighbors[current].iter() {
+            if reached_from[other].is_some() || (current == next && other == atom) {
+                continue;
+            }
+            reached_from[other] = Some(current);
+            if other == atom {
+                let mut ring = vec![atom];
+                let mut back = current;
+                while back != next {
+                    ring.push(back);
+                    back = reached_from[back]?;
+                }
+                ring.push(next);
+                ring.reverse();
+                ring.rotate_right(1);
+                return Some(ring);
+            }
+            queue.push_back(other);
+        }
+    }
+    None
+}
+
+// cells for the atoms of `ring` after its first, placed, one up to the next placed one: the ring_steps polygon,
+// started at any of its corners, walked either way, turned in 90 degree steps and mirrored, that puts them on free
+// cells and any other atoms of the ring on their own cells, lying away from `back` or crowding other atoms least;
+// failing that, for a ring fused to placed atoms, the path of free neighboring cells over to them that keeps
+// furthest from them
+fn ring_cells(ring: &[usize], cells: &[Option<Cell>], taken: &HashSet<Cell>, back: Option<usize>) -> Option<Vec<Cell>> {
+    let steps = ring_steps(ring.len())?;
+    let here = cells[ring[0]]?;
+    let unplaced = 1 + ring[1..].iter().take_while(|&&j| cells[j].is_none()).count();
+    if unplaced == 1 || ring[unplaced..].iter().any(|&j| cells[j].is_none()) {
+        return None;
+    }
+    let crowding = |path: &[Cell]| path.iter()
+        .map(|&cell| (0..8).filter(|&d| taken.contains(&step(cell, d, 1))).count() as i32)
+        .sum::<i32>();
+
+    let count = steps.len();
+    let walks = (0..count).flat_map(|start| (0..4).flat_map(move |turn| [(false, false), (false, true), (true, false), (true, true)]
+        .map(|(reversed, mirror)| (start, turn, reversed, mirror))));
+    let fitting = walks.filter_map(|(start, turn, reversed, mirror)| {
+        let mut cell = here;
+        let walk = (0..count - 1).map(|k| {
+            let d = if reversed { (steps[(start + count - 1 - k) % count] + 4) % 8 } else { steps[(start + k) % count] };
+            let d = if mirror { (8 - d) % 8 } else { d };
+            cell = step(cell, (d + 2 * turn) % 8, 1);
+            cell
+        }).collect::<Vec<_>>();
+        let fits = walk.iter().zip(ring[1..].iter()).all(|(cell, &j)| match cells[j] {
+            Some(placed) => placed == *cell,
+            None => !taken.contains(cell),
+        });
+        fits.then(|| walk[..unplaced - 1].to_vec())
+    });
+    let best = match back {
+        Some(back) => fitting.min_by_key(|walk| {
+            let (dr, dc) = STEPS[back];
+            walk.iter().map(|&(row, column)| (row - here.0) * dr + (column - here.1) * dc).sum::<i32>()
+        }),
+        None => fitting.min_by_key(|walk| crowding(walk)),
+    };
+    if best.is_some() || unplaced == ring.len() {
+        return best;
+    }
+
+    let end = cells[ring[unplaced]]?;
+    let placed = ring.iter().filter_map(|&j| cells[j]).collect::<Vec<_>>();
+    let middle = (placed.iter().map(|&(row, _)| row).sum::<i32>() as f64 / placed.len() as f64,
+        placed.iter().map(|&(_, column)| column).sum::<i32>() as f64 / placed.len() as f64);
+    let spread = |path: &[Cell]| path.iter()
+        .map(|&(row, column)| (row as f64 - middle.0).powi(2) + (column as f64 - middle.1).powi(2))
+        .sum::<f64>() - 2.0 * crowding(path) as f64;
+    let mut best: Option<(f64, Vec<Cell>)> = None;
+    let mut path = vec![];
+    extend_path(here, end, unplaced - 1, taken, &mut path, &mut |path| {
+        let score = spread(path);
+        if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+            best = Some((score, path.to_vec()));
+        }
+    });
+    best.map(|(_, path)| path)
+}
+
+// every path of `length` free cells, each next to the one before, from a neighbor of `from` to a neighbor of `to`
+fn extend_path(from: Cell, to: Cell, length: usize, taken: &HashSet<Cell>, path: &mut Vec<Cell>, found: &mut dyn FnMut(&[Cell])) {
+    if path.len() == length {
+        if direction_between(*path.last().unwrap_or(&from), to).is_some() {
+            found(path);
+        }
+        return;
+    }
+    let current = *path.last().unwrap_or(&from);
+    for d in 0..8 {
+        let cell = step(current, d, 1);
+        // the rest of the way has to be able to reach `to`
+        let left = (length - path.len()) as i32;
+        if taken.contains(&cell) || path.contains(&cell) || cell == to || (cell.0 - to.0).abs().max((cell.1 - to.1).abs()) > left {
+            continue;
+        }
+        path.push(cell);
+        extend_path(from, to, length, taken, path, found);
+        path.pop();
+    }
+}
+
+// direction from `cell` that points most directly away from the middle of `ring`
+fn outward(cell: Cell, ring: &[Cell]) -> usize {
+    let count = ring.len() as f64;
+    let middle = (ring.iter().map(|&(row, _)| row as f64).sum::<f64>() / count, ring.iter().map(|&(_, column)| column as f64).sum::<f64>() / count);
+    let away = (cell.0 as f64 - middle.0, cell.1 as f64 - middle.1);
+    (0..8).max_by(|&a, &b| {
+        let along = |d: usize| {
+            let (dr, dc) = STEPS[d];
+            (dr as f64 * away.0 + dc as f64 * away.1) / ((dr * dr + dc * dc) as f64).sqrt()
+        };
+        along(a).total_cmp(&along(b))
+    }).unwrap_or(E)
+}
+
+// where grid_positions put the atoms
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridLayout {
+    // (row, column) of each atom
+    pub cells: Vec<(i32, i32)>,
+    // bonds that leave the page, from the atom whose shape they belong to (the narrow end of a wedge)
+    pub wedges: Vec<(usize, usize, Depth)>,
+}
+
+// grid cells (row, column) of the atoms: a breadth first walk from the atom with the most bonds lays each atom's
+// neighbors out in a flat projection of its VSEPR shape, turned in 45 degree steps so that one of its directions
+// points back at the atom it was reached from; crowded atoms spill over into the other directions, then further
+// out, and disconnected pieces are set side by side. A ring of up to 8 atoms reached through one of its atoms is
+// laid out whole as a polygon on the grid and a ring fused to placed atoms as a path of free cells around to them,
+// and the other bonds of ring atoms point away from the middle of their ring
+pub fn grid_positions(atoms: &[DiagramAtom], bonds: &[(usize, usize, u32)]) -> GridLayout {
+    let mut neighbors: Vec<Vec<usize>> = vec![vec![]; atoms.len()];
+    for &(a, b, _) in bonds {
+        neighbors[a].push(b);
+        neighbors[b].push(a);
+    }
+    for list in neighbors.iter_mut() {
+        list.sort_by_key(|&j| (atoms[j].symbol.trim() == "H", j));
+    }
+
+    let mut cells: Vec<Option<(i32, i32)>> = vec![None; atoms.len()];
+    // direction pointing out of the ring an atom was placed in
+    let mut ring_outward: Vec<Option<usize>> = vec![None; atoms.len()];
+    let mut wedges = vec![];
+    let mut offset = 0;
+    while let Some(start) = (0..atoms.len()).rev().filter(|&i| cells[i].is_none()).max_by_key(|&i| neighbors[i].len()) {
+        cells[start] = Some((0, 0));
+        let mut taken = HashSet::from([(0, 0)]);
+        // atom and the direction back to where it was reached from
+        let mut queue = VecDeque::from([(start, None::<usize>)]);
+        let mut piece = vec![start];
+        while let Some((atom, back)) = queue.pop_front() {
+            let here = cells[atom].unwrap_or_default();
+            let shape = projection(atoms[atom].geometry);
+            let turn = back
+                .and_then(|back| [0, 2, 6, 4, 1, 7, 3, 5].into_iter().find(|&k| shape.iter().any(|&(d, _)| (d + k) % 8 == back)))
+                .unwrap_or(0);
+            let away = back.map(|back| [4, 2, 6, 3, 5, 1, 7].map(|k| (back + k) % 8)).unwrap_or_default();
+
+            for &next in neighbors[atom].iter() {
+                if cells[next].is_some() {
+                    continue;
+                }
+                // the ring through the bond, then every ring fused to it, before anything else takes their cells
+                let mut ring_bonds = vec![(atom, next, back)];
+                while let Some((a, b, back)) = ring_bonds.pop() {
+                    let Some(ring) = smallest_ring(&neighbors, a, b) else { continue };
+                    let Some(new_cells) = ring_cells(&ring, &cells, &taken, back) else { continue };
+                    for (&j, &cell) in ring[1..].iter().zip(new_cells.iter()) {
+                        cells[j] = Some(cell);
+                        taken.insert(cell);
+                        piece.push(j);
+                        queue.push_back((j, None));
+                    }
+                    let cycle = ring.iter().filter_map(|&j| cells[j]).collect::<Vec<_>>();
+                    for (&j, &cell) in ring.iter().zip(cycle.iter()) {
+                        if ring_outward[j].is_none() {
+                            ring_outward[j] = Some(outward(cell, &cycle));
+                        }
+                        ring_bonds.extend(neighbors[j].iter().filter(|&&k| cells[k].is_none()).map(|&k| (j, k, None)));
+                    }
+                }
+                if cells[next].is_some() {
+                    continue;
+                }
+
+                // atoms of a ring put their other bonds outside it, nearest the direction straight out first
+                let preferred = match ring_outward[atom] {
+                    Some(out) => [0, 1, 7, 2, 6, 3, 5, 4].map(|k| (out + k) % 8).to_vec(),
+                    None => shape.iter().map(|&(d, _)| (d + turn) % 8).chain(away).chain(0..8).collect::<Vec<_>>(),
+                };
+                // next to its other placed neighbors if possible, so that rings close
+                let placed = neighbors[next].iter().filter_map(|&j| cells[j]).filter(|&cell| cell != here).collect::<Vec<_>>();
+                let closes_ring = |cell: &(i32, i32)| placed.iter().all(|&other| direction_between(*cell, other).is_some());
+                let cell = preferred.iter().map(|&d| step(here, d, 1)).filter(|cell| !taken.contains(cell)).find(closes_ring)
+                    .into_iter()
+                    .chain(preferred.iter().map(|&d| step(here, d, 1)))
+                    .chain((2..).flat_map(|distance| (0..8).map(move |d| step(here, d, distance))))
+                    .find(|cell| !taken.contains(cell))
+                    .unwrap_or(here);
+                cells[next] = Some(cell);
+                taken.insert(cell);
+                let depth = shape.iter().filter(|_| ring_outward[atom].is_none())
+                    .find(|&&(d, _)| Some((d + turn) % 8) == direction_between(here, cell)).map(|&(_, depth)| depth);
+                if let Some(depth @ (Depth::Front | Depth::Back)) = depth {
+                    wedges.push((atom, next, depth));
+                }
+                queue.push_back((next, direction_between(cell, here)));
+                piece.push(next);
+            }
+        }
+
+        let left = piece.iter().filter_map(|&i| cells[i]).map(|(_, column)| column).min().unwrap_or(0);
+        for &i in piece.iter() {
+            cells[i] = cells[i].map(|(row, column)| (row, column - left + offset));
+        }
+        offset = piece.iter().filter_map(|&i| cells[i]).map(|(_, column)| column).max().unwrap_or(offset) + 2;
+    }
+
+    GridLayout { cells: cells.into_iter().map(Option::unwrap_or_default).collect(), wedges }
+}
+
+// characters of the drawing by (line, column)
+#[derive(Default)]
+struct Canvas {
+    chars: HashMap<(i32, i32), char>,
+}
+
+impl Canvas {
+    fn write(&mut self, line: i32, column: i32, text: &str) {
+        for (i, c) in text.chars().enumerate() {
+            if c != ' ' {
+                self.chars.insert((line, column + i as i32), c);
+            }
+        }
+    }
+
+    fn is_blank(&self, line: i32, column: i32, text: &str) -> bool {
+        (0..text.chars().count() as i32).all(|i| !self.chars.contains_key(&(line, column + i)))
+    }
+
+    // blank with a blank column on either side as well, so that dots and charges do not run into each other
+    fn is_clear(&self, line: i32, column: i32, text: &str) -> bool {
+        self.is_blank(line, column - 1, &format!("  {}", text))
+    }
+
+    fn to_text(&self) -> String {
+        let Some(top) = self.chars.keys().map(|&(line, _)| line).min() else { return String::new() };
+        let bottom = self.chars.keys().map(|&(line, _)| line).max().unwrap_or(top);
+        let left = self.chars.keys().map(|&(_, column)| column).min().unwrap_or(0);
+        let right = self.chars.keys().map(|&(_, column)| column).max().unwrap_or(left);
+        let mut text = String::new();
+        for line in top..=bottom {
+            let row = (left..=right).map(|column| self.chars.get(&(line, column)).copied().unwrap_or(' ')).collect::<String>();
+            text.push_str(row.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+// where on the canvas the electrons on side `d` of an atom drawn at (line, column) go; symbols are two columns wide
+fn electrons(d: usize, count: u32, line: i32, column: i32) -> (i32, i32, &'static str) {
+    let pair = count == 2;
+    match d {
+        N => (line - 1, column, if pair { ".." } else { "." }),
+        S => (line + 1, column, if pair { "˙˙" } else { "˙" }),
+        W => (line, column - 1, if pair { ":" } else { "·" }),
+        E => (line, column + 2, if pair { ":" } else { "·" }),
+        NE => (line - 1, column + 2, if pair { ".." } else { "." }),
+        SE => (line + 1, column + 2, if pair { "˙˙" } else { "˙" }),
+        NW => if pair { (line - 1, column - 2, "..") } else { (line - 1, column - 1, ".") },
+        _ => if pair { (line + 1, column - 2, "˙˙") } else { (line + 1, column - 1, "˙") },
+    }
+}
+
+// formal charge as written next to an atom, `+`, `2+`, and so on, with the given minus sign for negative ones
+pub fn charge_text(charge: i32, minus: char) -> String {
+    match charge {
+        1 => "+".to_string(),
+        -1 => minus.to_string(),
+        c if c > 0 => format!("{}+", c),
+        c => format!("{}{}", -c, minus),
+    }
+}
+
+// bond symbols for the bond from an atom to its neighbor in direction `d` (east or somewhere below) and where they
+// start, relative to the atom
+fn bond_text(d: usize, order: u32) -> (i32, i32, String) {
+    let order = order.clamp(1, 3) as usize;
+    match d {
+        E => (0, 2, ["--", "==", "≡≡"][order - 1].to_string()),
+        S => (1, 0, "|".repeat(order)),
+        SE => (1, 2, "\\".repeat(order)),
+        _ => (1, -(order as i32), "/".repeat(order)),
+    }
+}
+
+// the Lewis diagram as text: each atom sits on a grid from grid_positions with its symbol, lone pairs as `..`, `˙˙`
+// and `:` around it and its formal charge in a free corner; bonds between neighboring cells are drawn as `--`, `|`,
+// `/` and `\` (doubled or tripled for double and triple bonds) and any others, such as bonds closing a ring, are
+// listed below the drawing with the atoms named by symbol and id
+pub fn render_ascii(atoms: &[DiagramAtom], bonds: &[(usize, usize, u32)]) -> String {
+    let cells = grid_positions(atoms, bonds).cells;
+    let occupied = cells.iter().copied().collect::<HashSet<_>>();
+    let origin = |i: usize| (2 * cells[i].0, 4 * cells[i].1);
+    let mut canvas = Canvas::default();
+
+    let mut sides: Vec<Vec<usize>> = vec![vec![]; atoms.len()];
+    let mut undrawn = vec![];
+    for &(a, b, order) in bonds {
+        let Some(d) = direction_between(cells[a], cells[b]) else {
+            undrawn.push((a, b, order));
+            continue;
+        };
+        sides[a].push(d);
+        sides[b].push((d + 4) % 8);
+        // drawn from the atom that is further up, or further left on the same line
+        let (from, d) = if matches!(d, E | SW | S | SE) { (a, d) } else { (b, (d + 4) % 8) };
+        let (line, column) = origin(from);
+        let (dl, dc, text) = bond_text(d, order);
+        canvas.write(line + dl, column + dc, &text);
+    }
+    for (i, atom) in atoms.iter().enumerate() {
+        let (line, column) = origin(i);
+        canvas.write(line, column, &atom.symbol);
+    }
+
+    for (i, atom) in atoms.iter().enumerate() {
+        let (line, column) = origin(i);
+        let mut groups = vec![2; (atom.lone / 2) as usize];
+        if atom.lone % 2 == 1 {
+            groups.push(1);
+        }
+        // free sides, those crowded by other dots or facing other atoms last
+        let mut free = SLOTS.iter().copied().filter(|d| !sides[i].contains(d)).collect::<Vec<_>>();
+        free.sort_by_key(|&d| {
+            let (l, c, text) = electrons(d, 2, line, column);
+            let crowded = l != line && !canvas.is_clear(l, c, text);
+            (!canvas.is_blank(l, c, text), crowded, d % 2 == 1, occupied.contains(&step(cells[i], d, 1)))
+        });
+        let mut free = free.into_iter();
+        for count in groups {
+            let Some(d) = free.next() else { break };
+            let (l, c, text) = electrons(d, count, line, column);
+            canvas.write(l, c, text);
+        }
+        if atom.charge != 0 {
+            let text = charge_text(atom.charge, '-');
+            let width = text.chars().count() as i32;
+            let corners = [(NE, line - 1, column + 3), (SE, line + 1, column + 3), (NW, line - 1, column - width - 1), (SW, line + 1, column - width - 1)];
+            let (_, l, c) = corners.iter().copied()
+                .find(|&(d, l, c)| free.as_slice().contains(&d) && canvas.is_clear(l, c, &text))
+                .unwrap_or(corners[0]);
+            canvas.write(l, c, &text);
+        }
+    }
+
+    let mut text = canvas.to_text();
+    if !undrawn.is_empty() {
+        let label = |i: usize| format!("{}{}", atoms[i].symbol.trim(), atoms[i].id);
+        let listed = undrawn.iter()
+            .map(|&(a, b, order)| format!("{}{}{}", label(a), ["-", "=", "#"][order.clamp(1, 3) as usize - 1], label(b)))
+            .collect::<Vec<_>>();
+        text.push_str(&format!("also bonded: {}\n", listed.join(", ")));
+    }
+    text
+}
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use csv::{Reader, StringRecord};
+use crate::error::LewisError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StandardState {
+    Gas,
+    Liquid,
+    Solid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BondingType {
+    Atomic,
+    CovalentNetwork,
+    Diatomic,
+    Metallic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBlock {
+    AlkaliMetal,
+    AlkalineEarthMetal,
+    TransitionMetal,
+    PostTransitionMetal,
+    Metal,
+    Metalloid,
+    Nonmetal,
+    Halogen,
+    NobleGas,
+    Lanthanoid,
+    Actinoid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YearDiscovered {
+    Ancient,
+    Year(u32),
+}
+
+// ionic radius in pm, for the ion with the given charge
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IonRadius {
+    pub radius: f64,
+    pub charge: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+    // symbol, padded to two characters
+    pub(crate) name: String,
+    pub(crate) electroneg: u32,
+    // counts atoms of the same element within one molecule, 0 in the table itself
+    pub(crate) id: u32,
+    pub(crate) atomic_number: u32,
+    // full name, e.g. "Oxygen"
+    pub(crate) element_name: String,
+    // in u; for elements without stable isotopes this is the mass number of the longest lived one
+    pub(crate) atomic_mass: f64,
+    // rgb
+    pub(crate) cpk_color: Option<[u8; 3]>,
+    // full electron configuration, e.g. "[He] 2s2 2p4"
+    pub(crate) electron_configuration: String,
+    // Pauling scale
+    pub(crate) electronegativity: Option<f64>,
+    // radii in pm
+    pub(crate) atomic_radius: Option<f64>,
+    pub(crate) ion_radius: Option<IonRadius>,
+    pub(crate) van_der_waals_radius: Option<f64>,
+    // kJ/mol
+    pub(crate) ionization_energy: Option<f64>,
+    pub(crate) electron_affinity: Option<f64>,
+    pub(crate) oxidation_states: Vec<i32>,
+    pub(crate) standard_state: Option<StandardState>,
+    pub(crate) bonding_type: Option<BondingType>,
+    // in K
+    pub(crate) melting_point: Option<f64>,
+    pub(crate) boiling_point: Option<f64>,
+    // g/cm3
+    pub(crate) density: Option<f64>,
+    pub(crate) group_block: Option<GroupBlock>,
+    pub(crate) year_discovered: Option<YearDiscovered>,
+}
+
+impl Element {
+    pub fn symbol(&self) -> &str {
+        self.name.trim()
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn atomic_number(&self) -> u32 {
+        self.atomic_number
+    }
+
+    pub fn element_name(&self) -> &str {
+        &self.element_name
+    }
+
+    pub fn atomic_mass(&self) -> f64 {
+        self.atomic_mass
+    }
+
+    pub fn cpk_color(&self) -> Option<[u8; 3]> {
+        self.cpk_color
+    }
+
+    pub fn electron_configuration(&self) -> &str {
+        &self.electron_configuration
+    }
+
+    pub fn electronegativity(&self) -> Option<f64> {
+        self.electronegativity
+    }
+
+    pub fn atomic_radius(&self) -> Option<f64> {
+        self.atomic_radius
+    }
+
+    pub fn ion_radius(&self) -> Option<IonRadius> {
+        self.ion_radius
+    }
+
+    pub fn van_der_waals_radius(&self) -> Option<f64> {
+        self.van_der_waals_radius
+    }
+
+    pub fn ionization_energy(&self) -> Option<f64> {
+        self.ionization_energy
+    }
+
+    pub fn electron_affinity(&self) -> Option<f64> {
+        self.electron_affinity
+    }
+
+    pub fn oxidation_states(&self) -> &[i32] {
+        &self.oxidation_states
+    }
+
+    pub fn standard_state(&self) -> Option<StandardState> {
+        self.standard_state
+    }
+
+    pub fn bonding_type(&self) -> Option<BondingType> {
+        self.bonding_type
+    }
+
+    pub fn melting_point(&self) -> Option<f64> {
+        self.melting_point
+    }
+
+    pub fn boiling_point(&self) -> Option<f64> {
+        self.boiling_point
+    }
+
+    pub fn density(&self) -> Option<f64> {
+        self.density
+    }
+
+    pub fn group_block(&self) -> Option<GroupBlock> {
+        self.group_block
+    }
+
+    pub fn year_discovered(&self) -> Option<YearDiscovered> {
+        self.year_discovered
+    }
+
+    // electrons in the outermost s and p subshells, e.g. 6 for `[Xe] 4f14 5d10 6s2 6p4`
+    pub fn valence_electrons(&self) -> Result<u32, LewisError> {
+        let bad = || LewisError::BadElementData(
+            format!("cannot read electron configuration '{}' of {}", self.electron_configuration, self.name.trim()));
+
+        let mut subshells = vec![];
+        for subshell in self.electron_configuration.split_whitespace().filter(|x| !x.starts_with('[')) {
+            let kind = subshell.find(|c: char| c.is_ascii_alphabetic()).ok_or_else(bad)?;
+            let shell = subshell[..kind].parse::<u32>().map_err(|_| bad())?;
+            let electrons = subshell[kind + 1..].parse::<u32>().map_err(|_| bad())?;
+            subshells.push((shell, &subshell[kind..kind + 1], electrons));
+        }
+
+        let outer_shell = subshells.iter().filter(|x| x.1 == "s").map(|x| x.0).max().ok_or_else(bad)?;
+        Ok(subshells.iter()
+            .filter(|x| x.0 == outer_shell && (x.1 == "s" || x.1 == "p"))
+            .map(|x| x.2)
+            .sum())
+    }
+}
+
+// data.csv compiled into the binary so that it does not depend on where it was built
+const EMBEDDED_CSV: &str = include_str!("../data/data.csv");
+
+// every row of data.csv, read once and indexed by symbol and atomic number
+#[derive(Debug, Clone)]
+pub struct ElementTable {
+    elements: Vec<Element>,
+    by_symbol: HashMap<String, usize>,
+    by_atomic_number: HashMap<u32, usize>,
+}
+
+// one row of data.csv; errors name the line, the element and the column that could not be read
+struct Row<'a> {
+    record: &'a StringRecord,
+    symbol: String,
+}
+
+impl Row<'_> {
+    fn cell(&self, column: usize) -> &str {
+        self.record.get(column).unwrap_or("").trim()
+    }
+
+    fn bad(&self, column: &str) -> LewisError {
+        let line = self.record.position().map_or(0, |position| position.line());
+        LewisError::BadElementData(format!("line {}: cannot read {} of {}", line, column, self.symbol))
+    }
+
+    fn number(&self, column: usize, name: &str) -> Result<Option<f64>, LewisError> {
+        match self.cell(column) {
+            "" => Ok(None),
+            cell => cell.parse::<f64>().map(Some).map_err(|_| self.bad(name)),
+        }
+    }
+
+    // `1.00794(4)` has its uncertainty in parentheses, `[209]` is a mass number
+    fn atomic_mass(&self, column: usize) -> Result<f64, LewisError> {
+        let cell = self.cell(column);
+        let value = match cell.find('(') {
+            Some(end) => &cell[..end],
+            None => cell.trim_start_matches('[').trim_end_matches(']'),
+        };
+        value.trim().parse::<f64>().map_err(|_| self.bad("atomic mass"))
+    }
+
+    fn cpk_color(&self, column: usize) -> Option<[u8; 3]> {
+        // some colors were mangled into numbers by a spreadsheet, those are treated as missing
+        let cell = self.cell(column);
+        if cell.len() != 6 || !cell.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&cell[i..i + 2], 16).ok();
+        Some([channel(0)?, channel(2)?, channel(4)?])
+    }
+
+    // `76 (+1)`, with an occasional footnote marker as in `80 (+2*)`
+    fn ion_radius(&self, column: usize) -> Result<Option<IonRadius>, LewisError> {
+        let cell = self.cell(column);
+        if cell.is_empty() {
+            return Ok(None);
+        }
+        let (radius, charge) = cell.split_once('(').ok_or_else(|| self.bad("ionic radius"))?;
+        let charge = charge.trim_end_matches(')').trim_end_matches('*').trim_start_matches('+');
+        Ok(Some(IonRadius {
+            radius: radius.trim().parse().map_err(|_| self.bad("ionic radius"))?,
+            charge: charge.parse().map_err(|_| self.bad("ionic radius charge"))?,
+        }))
+    }
+
+    fn oxidation_states(&self, column: usize) -> Result<Vec<i32>, LewisError> {
+        self.cell(column).split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(|x| x.parse::<i32>().map_err(|_| self.bad("oxidation states")))
+            .collect()
+    }
+
+    fn standard_state(&self, column: usize) -> Result<Option<StandardState>, LewisError> {
+        match self.cell(column) {
+            "" => Ok(None),
+            "gas" => Ok(Some(StandardState::Gas)),
+            "liquid" => Ok(Some(StandardState::Liquid)),
+            "solid" => Ok(Some(StandardState::Solid)),
+            _ => Err(self.bad("standard state")),
+        }
+    }
+
+    fn bonding_type(&self, column: usize) -> Result<Option<BondingType>, LewisError> {
+        match self.cell(column) {
+            "" => Ok(None),
+            "atomic" => Ok(Some(BondingType::Atomic)),
+            "covalent network" => Ok(Some(BondingType::CovalentNetwork)),
+            "diatomic" => Ok(Some(BondingType::Diatomic)),
+            "metallic" => Ok(Some(BondingType::Metallic)),
+            _ => Err(self.bad("bonding type")),
+        }
+    }
+
+    fn group_block(&self, column: usize) -> Result<Option<GroupBlock>, LewisError> {
+        match self.cell(column) {
+            "" => Ok(None),
+            "alkali metal" => Ok(Some(GroupBlock::AlkaliMetal)),
+            "alkaline earth metal" => Ok(Some(GroupBlock::AlkalineEarthMetal)),
+            "transition metal" => Ok(Some(GroupBlock::TransitionMetal)),
+            "post-transition metal" => Ok(Some(GroupBlock::PostTransitionMetal)),
+            "metal" => Ok(Some(GroupBlock::Metal)),
+            "metalloid" => Ok(Some(GroupBlock::Metalloid)),
+            "nonmetal" => Ok(Some(GroupBlock::Nonmetal)),
+            "halogen" => Ok(Some(GroupBlock::Halogen)),
+            "noble gas" => Ok(Some(GroupBlock::NobleGas)),
+            "lanthanoid" => Ok(Some(GroupBlock::Lanthanoid)),
+            "actinoid" => Ok(Some(GroupBlock::Actinoid)),
+            _ => Err(self.bad("group block")),
+        }
+    }
+
+    fn year_discovered(&self, column: usize) -> Result<Option<YearDiscovered>, LewisError> {
+        match self.cell(column) {
+            "" => Ok(None),
+            "Ancient" => Ok(Some(YearDiscovered::Ancient)),
+            year => year.parse().map(|x| Some(YearDiscovered::Year(x))).map_err(|_| self.bad("year discovered")),
+        }
+    }
+}
+
+impl ElementTable {
+    pub fn embedded() -> Result<Self, LewisError> {
+        Self::from_reader(EMBEDDED_CSV.as_bytes())
+    }
+
+    pub fn read_csv(file_path: &str) -> Result<Self, LewisError> {
+        let file = File::open(file_path)
+            .map_err(|e| LewisError::BadElementData(format!("cannot open {}: {}", file_path, e)))?;
+        Self::from_reader(file)
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, LewisError> {
+        let mut table = ElementTable { elements: vec![], by_symbol: HashMap::new(), by_atomic_number: HashMap::new() };
+
+        let mut rdr = Reader::from_reader(reader);
+        for result in rdr.records() {
+            let record = result?;
+            let row = Row { record: &record, symbol: record.get(1).unwrap_or("").trim().to_string() };
+
+            let atomic_number = row.cell(0).parse::<u32>().map_err(|_| row.bad("atomic number"))?;
+
+            let mut symbol = row.symbol.replace("\t", "").replace(" ", "");
+            let key = symbol.clone();
+            if symbol.len() == 1 {
+                symbol.push(' ');
+            }
+
+            let electronegativity = row.number(6, "electronegativity")?;
+
+            let element = Element {
+                name: symbol,
+                electroneg: (electronegativity.unwrap_or(0.0) * 100.0) as u32,
+                id: 0,
+                atomic_number,
+                element_name: row.cell(2).to_string(),
+                atomic_mass: row.atomic_mass(3)?,
+                cpk_color: row.cpk_color(4),
+                electron_configuration: row.cell(5).to_string(),
+                electronegativity,
+                atomic_radius: row.number(7, "atomic radius")?,
+                ion_radius: row.ion_radius(8)?,
+                van_der_waals_radius: row.number(9, "van der Waals radius")?,
+                ionization_energy: row.number(10, "ionization energy")?,
+                electron_affinity: row.number(11, "electron affinity")?,
+                oxidation_states: row.oxidation_states(12)?,
+                standard_state: row.standard_state(13)?,
+                bonding_type: row.bonding_type(14)?,
+                melting_point: row.number(15, "melting point")?,
+                boiling_point: row.number(16, "boiling point")?,
+                density: row.number(17, "density")?,
+                group_block: row.group_block(18)?,
+                year_discovered: row.year_discovered(19)?,
+            };
+
+            table.by_symbol.insert(key, table.elements.len());
+            table.by_atomic_number.insert(atomic_number, table.elements.len());
+            table.elements.push(element);
+        }
+
+        Ok(table)
+    }
+
+    pub fn by_symbol(&self, symbol: &str) -> Option<&Element> {
+        self.by_symbol.get(symbol.trim()).map(|&i| &self.elements[i])
+    }
+
+    pub fn by_atomic_number(&self, atomic_number: u32) -> Option<&Element> {
+        self.by_atomic_number.get(&atomic_number).map(|&i| &self.elements[i])
+    }
+
+    // looks up every symbol in order, numbering repeated elements with increasing ids
+    pub fn elements_for(&self, element_names: &[String]) -> Result<Vec<Element>, LewisError> {
+        let mut seen: HashMap<&str, u32> = HashMap::new();
+        element_names.iter().map(|element_name| {
+            let mut element = self.by_symbol(element_name)
+                .ok_or_else(|| LewisError::UnknownElement(element_name.clone()))?
+                .clone();
+            let count = seen.entry(element_name.as_str()).or_insert(0);
+            element.id = *count;
+            *count += 1;
+            Ok(element)
+        }).collect()
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+use crate::formula::FormulaError;
+use crate::smiles::SmilesError;
+
+// what went wrong in a PositionedError
+pub trait PositionedErrorKind: fmt::Debug {
+    // message for the error found at `position`
+    fn describe(&self, position: usize) -> String;
+
+    // whether there is a character to point at, which there is not for empty input
+    fn has_position(&self) -> bool;
+}
+
+// error with the (char based) position of the offending character in the input, as the formula and SMILES parsers
+// report them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionedError<K> {
+    pub input: String,
+    pub position: usize,
+    pub kind: K,
+}
+
+impl<K: PositionedErrorKind> fmt::Display for PositionedError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind.describe(self.position))?;
+        if self.kind.has_position() {
+            // point at the offending character underneath the input
+            write!(f, "\n  {}\n  {}^", self.input, " ".repeat(self.position))?;
+        }
+        Ok(())
+    }
+}
+
+impl<K: PositionedErrorKind> std::error::Error for PositionedError<K> {}
+
+#[derive(Debug)]
+pub enum LewisError {
+    // formula could not be parsed
+    Formula(FormulaError),
+    // SMILES could not be parsed
+    Smiles(SmilesError),
+    // symbol that is not in the element table
+    UnknownElement(String),
+    // charge that is not a number or that conflicts with the charge written in the formula
+    BadCharge(String),
+    // element table that cannot be opened or has malformed data the solver needs
+    BadElementData(String),
+    // bonds given by the user that do not make a usable skeleton
+    BadConnectivity(String),
+    // solver could not place every atom and electron
+    NoValidStructure(String),
+    // formal charges of the finished structure do not add up to the charge of the molecule
+    FormalChargeMismatch { expected: i32, found: i32 },
+    // bond order search gave up after trying this many assignments, without knowing the best one
+    SearchLimit(usize),
+    // atom needs more hybrid orbitals than SP3D5 has
+    HybridizationOverflow,
+    // json model that cannot be read back, see Model::from_json
+    BadJson(String),
+    Io(std::io::Error),
+    Csv(csv::Error),
+}
+
+impl fmt::Display for LewisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LewisError::Formula(e) => write!(f, "invalid formula: {}", e),
+            LewisError::Smiles(e) => write!(f, "invalid SMILES: {}", e),
+            LewisError::UnknownElement(symbol) => write!(f, "unknown element '{}'", symbol),
+            LewisError::BadCharge(reason) => write!(f, "bad charge: {}", reason),
+            LewisError::BadElementData(reason) => write!(f, "bad element data: {}", reason),
+            LewisError::BadConnectivity(reason) => write!(f, "bad connectivity: {}", reason),
+            LewisError::NoValidStructure(reason) => write!(f, "no valid Lewis structure: {}", reason),
+            LewisError::FormalChargeMismatch { expected, found } =>
+                write!(f, "formal charges add up to {} instead of the molecule's charge of {}", found, expected),
+            LewisError::SearchLimit(tried) =>
+                write!(f, "gave up after trying {} bond order assignments, the molecule is too large to solve", tried),
+            LewisError::HybridizationOverflow => write!(f, "cannot hybridize beyond SP3D5"),
+            LewisError::BadJson(reason) => write!(f, "bad json model: {}", reason),
+            LewisError::Io(e) => write!(f, "{}", e),
+            LewisError::Csv(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for LewisError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LewisError::Formula(e) => Some(e),
+            LewisError::Smiles(e) => Some(e),
+            LewisError::Io(e) => Some(e),
+            LewisError::Csv(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<FormulaError> for LewisError {
+    fn from(e: FormulaError) -> Self {
+        LewisError::Formula(e)
+    }
+}
+
+impl From<SmilesError> for LewisError {
+    fn from(e: SmilesError) -> Self {
+        LewisError::Smiles(e)
+    }
+}
+
+impl From<std::io::Error> for LewisError {
+    fn from(e: std::io::Error) -> Self {
+        LewisError::Io(e)
+    }
+}
+
+impl From<csv::Error> for LewisError {
+    fn from(e: csv::Error) -> Self {
+        LewisError::Csv(e)
+    }
+}
@@ -0,0 +1,664 @@
+use crate::error::{PositionedError, PositionedErrorKind};
+
+// grammar accepted by `parse_formula`:
+//
+//   formula := part (dot part)* charge?
+//   part    := count? group+
+//   group   := symbol count? | '(' group+ ')' count? | '[' group+ ']' count?
+//   symbol  := uppercase lowercase*
+//   count   := digit+
+//   dot     := '·' | '•' | '.' | '*'
+//   charge  := '^'? sign_body | '(' sign_body ')'
+//   sign_body := digit+ sign | sign digit* | sign sign+
+//   sign    := '+' | '-' | '−'
+//
+// condensed formulas (`parse_condensed`) use the same symbols, counts and charge but no dots or '[]':
+//
+//   condensed := unit+ charge?
+//   unit      := symbol count? | '(' unit+ ')' count?
+//
+// every uncounted non-hydrogen atom bonds to the one before it, which makes the main chain; counted carbons
+// (`C2H5`) extend the chain too, with the hydrogens after them filling each carbon in turn, while other counted
+// atoms (`Cl3`) and hydrogens bond to the last chain atom without extending it; a '(' group is a branch off it,
+// so `CH3CH(CH3)OH` is propan-2-ol, unless it is repeated and its last atom has room left, in which case each
+// copy continues the chain (`CH3(CH2)4CH3`); atoms written before the first chain atom (`HOCH3`, `(CH3)2CO`)
+// bond to it.
+// An oxygen after a carbon that still has room for a double bond is a carbonyl oxygen instead, which hangs off
+// the carbon without extending the chain unless a hydrogen follows it: `CH3COOH`, `HCOOH`, `CH3CHO` and
+// `CH3COCH3` read as a carbonyl group, and so does the first oxygen of `CO2H`, whose second one carries the chain
+//
+// the leading count of a part multiplies the whole part, so `CuSO4·5H2O` is CuSO4 plus five H2O;
+// the charge has to come last, and digits right before a sign are only read as the charge magnitude
+// after a ']' (as in `[PO4]3-`), otherwise they stay a count (as in `NH4+`)
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormulaErrorKind {
+    Empty,
+    UnexpectedChar(char),
+    UnexpectedEnd,
+    UnclosedGroup(char),
+    MismatchedClose { expected: char, found: char },
+    UnmatchedClose(char),
+    EmptyGroup,
+    ZeroCount,
+    CountOverflow,
+    MissingChargeSign,
+    ChargeNotAtEnd,
+}
+
+pub type FormulaError = PositionedError<FormulaErrorKind>;
+
+impl PositionedErrorKind for FormulaErrorKind {
+    fn describe(&self, position: usize) -> String {
+        match self {
+            FormulaErrorKind::Empty => "empty formula".to_string(),
+            FormulaErrorKind::UnexpectedChar(c) => format!("unexpected character '{}' at position {}", c, position),
+            FormulaErrorKind::UnexpectedEnd => format!("unexpected end of formula at position {}", position),
+            FormulaErrorKind::UnclosedGroup(c) => format!("group opened with '{}' at position {} is never closed", c, position),
+            FormulaErrorKind::MismatchedClose { expected, found } =>
+                format!("expected '{}' but found '{}' at position {}", expected, found, position),
+            FormulaErrorKind::UnmatchedClose(c) => format!("'{}' at position {} has no matching opening bracket", c, position),
+            FormulaErrorKind::EmptyGroup => format!("empty group at position {}", position),
+            FormulaErrorKind::ZeroCount => format!("count of zero at position {}", position),
+            FormulaErrorKind::CountOverflow => format!("count too large at position {}", position),
+            FormulaErrorKind::MissingChargeSign => format!("expected '+' or '-' at position {}", position),
+            FormulaErrorKind::ChargeNotAtEnd => format!("charge has to be at the end of the formula, found more at position {}", position),
+        }
+    }
+
+    fn has_position(&self) -> bool {
+        *self != FormulaErrorKind::Empty
+    }
+}
+
+// one element symbol together with how many times it occurs at that place in the formula
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormulaTerm {
+    pub symbol: String,
+    pub count: u32,
+}
+
+// formula terms with the charge written in the formula, if any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedFormula {
+    pub terms: Vec<FormulaTerm>,
+    // formula text without the charge suffix
+    pub formula: String,
+    pub charge: Option<i32>,
+    // molecules or ions written apart, e.g. 2 for `CuSO4·5H2O`
+    pub fragments: usize,
+}
+
+// condensed formula with one term per atom, in order of appearance, and the bonds between them as term indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CondensedFormula {
+    pub parsed: ParsedFormula,
+    pub bonds: Vec<(usize, usize)>,
+}
+
+// atoms and bonds of a condensed formula as they are read
+#[derive(Default)]
+struct Connectivity {
+    terms: Vec<FormulaTerm>,
+    bonds: Vec<(usize, usize)>,
+}
+
+impl Connectivity {
+    fn add(&mut self, symbol: &str) -> usize {
+        self.terms.push(FormulaTerm { symbol: symbol.to_string(), count: 1 });
+        self.terms.len() - 1
+    }
+
+    fn degree(&self, atom: usize) -> usize {
+        self.bonds.iter().filter(|&&(a, b)| a == atom || b == atom).count()
+    }
+
+    // whether the atom makes fewer bonds so far than it usually does, e.g. the C of a CH2 unit
+    fn has_room(&self, atom: usize) -> bool {
+        self.degree(atom) < usual_bonds(&self.terms[atom].symbol)
+    }
+}
+
+// bonds a neutral atom usually makes, 0 for elements a condensed formula does not build chains of
+fn usual_bonds(symbol: &str) -> usize {
+    match symbol {
+        "C" | "Si" => 4,
+        "N" | "P" | "B" => 3,
+        "O" | "S" => 2,
+        "H" | "F" | "Cl" | "Br" | "I" => 1,
+        _ => 0,
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+fn closing_of(open: char) -> char {
+    match open {
+        '(' => ')',
+        _ => ']',
+    }
+}
+
+fn is_dot(c: char) -> bool {
+    matches!(c, '·' | '•' | '.' | '*')
+}
+
+fn sign_of(c: char) -> Option<i32> {
+    match c {
+        '+' => Some(1),
+        '-' | '−' => Some(-1),
+        _ => None,
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser { input, chars: input.chars().collect(), pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, position: usize, kind: FormulaErrorKind) -> FormulaError {
+        FormulaError { input: self.input.to_string(), position, kind }
+    }
+
+    fn formula(&mut self) -> Result<ParsedFormula, FormulaError> {
+        if self.chars.is_empty() {
+            return Err(self.error(0, FormulaErrorKind::Empty));
+        }
+
+        let mut terms = self.part()?;
+        let mut fragments = 1;
+        let mut charge = None;
+        let mut formula_end = self.chars.len();
+        while let Some(c) = self.peek() {
+            if self.at_charge() {
+                formula_end = self.pos;
+                charge = Some(self.charge()?);
+                if self.peek().is_some() {
+                    return Err(self.error(self.pos, FormulaErrorKind::ChargeNotAtEnd));
+                }
+            } else if is_dot(c) {
+                self.pos += 1;
+                terms.extend(self.part()?);
+                fragments += 1;
+            } else if c == ')' || c == ']' {
+                return Err(self.error(self.pos, FormulaErrorKind::UnmatchedClose(c)));
+            } else {
+                return Err(self.error(self.pos, FormulaErrorKind::UnexpectedChar(c)));
+            }
+        }
+        Ok(ParsedFormula { terms, formula: self.chars[..formula_end].iter().collect(), charge, fragments })
+    }
+
+    // whether a charge suffix starts at the current position
+    fn at_charge(&self) -> bool {
+        let Some(c) = self.peek() else { return false };
+        if c == '^' || sign_of(c).is_some() {
+            return true;
+        }
+        let next = self.chars.get(self.pos + 1).copied();
+        if c == '(' {
+            // groups never start with a digit or a sign
+            return next.is_some_and(|n| n.is_ascii_digit() || sign_of(n).is_some());
+        }
+        if c.is_ascii_digit() && self.pos > 0 && self.chars[self.pos - 1] == ']' {
+            let digits_end = self.chars[self.pos..].iter().position(|d| !d.is_ascii_digit())
+                .map_or(self.chars.len(), |offset| self.pos + offset);
+            return self.chars.get(digits_end).is_some_and(|&n| sign_of(n).is_some());
+        }
+        false
+    }
+
+    fn charge(&mut self) -> Result<i32, FormulaError> {
+        if self.peek() == Some('^') {
+            self.pos += 1;
+        }
+        let open_pos = self.pos;
+        let in_parens = self.peek() == Some('(');
+        if in_parens {
+            self.pos += 1;
+        }
+
+        let magnitude_pos = self.pos;
+        let (sign, magnitude) = match self.count()? {
+            Some(magnitude) => {
+                let sign = self.peek().and_then(sign_of)
+                    .ok_or_else(|| self.error(self.pos, FormulaErrorKind::MissingChargeSign))?;
+                self.pos += 1;
+                (sign, magnitude)
+            }
+            None => {
+                let sign = self.peek().and_then(sign_of)
+                    .ok_or_else(|| match self.peek() {
+                        Some(_) => self.error(self.pos, FormulaErrorKind::MissingChargeSign),
+                        None => self.error(self.pos, FormulaErrorKind::UnexpectedEnd),
+                    })?;
+                self.pos += 1;
+                if let Some(magnitude) = self.count()? {
+                    (sign, magnitude)
+                } else {
+                    // repeated signs, e.g. `Fe++`
+                    let mut magnitude = 1;
+                    while self.peek().and_then(sign_of) == Some(sign) {
+                        magnitude += 1;
+                        self.pos += 1;
+                    }
+                    (sign, magnitude)
+                }
+            }
+        };
+        let charge = i32::try_from(magnitude)
+            .map(|magnitude| sign * magnitude)
+            .map_err(|_| self.error(magnitude_pos, FormulaErrorKind::CountOverflow))?;
+
+        if in_parens {
+            match self.peek() {
+                Some(')') => self.pos += 1,
+                Some(c) => return Err(self.error(self.pos, FormulaErrorKind::UnexpectedChar(c))),
+                None => return Err(self.error(open_pos, FormulaErrorKind::UnclosedGroup('('))),
+            }
+        }
+        Ok(charge)
+    }
+
+    fn part(&mut self) -> Result<Vec<FormulaTerm>, FormulaError> {
+        let multiplier = self.count()?.unwrap_or(1);
+        let start = self.pos;
+        let mut terms = self.groups()?;
+        if terms.is_empty() {
+            return Err(match self.peek() {
+                Some(c) => self.error(self.pos, FormulaErrorKind::UnexpectedChar(c)),
+                None => self.error(start, FormulaErrorKind::UnexpectedEnd),
+            });
+        }
+        self.multiply(&mut terms, multiplier, start)?;
+        Ok(terms)
+    }
+
+    // parses groups until a character that cannot start a group is hit
+    fn groups(&mut self) -> Result<Vec<FormulaTerm>, FormulaError> {
+        let mut terms = vec![];
+        while let Some(c) = self.peek() {
+            if c.is_ascii_uppercase() {
+                let symbol = self.symbol();
+                let count = self.count()?.unwrap_or(1);
+                terms.push(FormulaTerm { symbol, count });
+            } else if (c == '(' || c == '[') && !self.at_charge() {
+                terms.extend(self.bracketed(c)?);
+            } else {
+                break;
+            }
+        }
+        Ok(terms)
+    }
+
+    fn bracketed(&mut self, open: char) -> Result<Vec<FormulaTerm>, FormulaError> {
+        let open_pos = self.pos;
+        self.pos += 1;
+        let mut terms = self.groups()?;
+
+        match self.peek() {
+            None => return Err(self.error(open_pos, FormulaErrorKind::UnclosedGroup(open))),
+            Some(c) if c == closing_of(open) => {
+                if terms.is_empty() {
+                    return Err(self.error(open_pos, FormulaErrorKind::EmptyGroup));
+                }
+                self.pos += 1;
+            }
+            Some(c) if c == ')' || c == ']' => {
+                return Err(self.error(self.pos, FormulaErrorKind::MismatchedClose { expected: closing_of(open), found: c }));
+            }
+            Some(c) => return Err(self.error(self.pos, FormulaErrorKind::UnexpectedChar(c))),
+        }
+
+        let count_pos = self.pos;
+        let multiplier = if self.at_charge() { 1 } else { self.count()?.unwrap_or(1) };
+        self.multiply(&mut terms, multiplier, count_pos)?;
+        Ok(terms)
+    }
+
+    fn symbol(&mut self) -> String {
+        let mut symbol = String::new();
+        symbol.push(self.chars[self.pos]);
+        self.pos += 1;
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_lowercase() {
+                break;
+            }
+            symbol.push(c);
+            self.pos += 1;
+        }
+        symbol
+    }
+
+    fn count(&mut self) -> Result<Option<u32>, FormulaError> {
+        let start = self.pos;
+        let mut count: Option<u32> = None;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(10)) {
+            count = Some(count.unwrap_or(0).checked_mul(10)
+                .and_then(|x| x.checked_add(digit))
+                .ok_or_else(|| self.error(start, FormulaErrorKind::CountOverflow))?);
+            self.pos += 1;
+        }
+        if count == Some(0) {
+            return Err(self.error(start, FormulaErrorKind::ZeroCount));
+        }
+        Ok(count)
+    }
+
+    fn condensed(&mut self) -> Result<CondensedFormula, FormulaError> {
+        if self.chars.is_empty() {
+            return Err(self.error(0, FormulaErrorKind::Empty));
+        }
+
+        let mut connectivity = Connectivity::default();
+        self.chain(&mut connectivity, None)?;
+        if connectivity.terms.is_empty() {
+            return Err(match self.peek() {
+                Some(c) => self.error(self.pos, FormulaErrorKind::UnexpectedChar(c)),
+                None => self.error(self.pos, FormulaErrorKind::UnexpectedEnd),
+            });
+        }
+
+        let formula_end = self.pos;
+        let mut charge = None;
+        if self.at_charge() {
+            charge = Some(self.charge()?);
+        }
+        match self.peek() {
+            None => {}
+            Some(_) if charge.is_some() => return Err(self.error(self.pos, FormulaErrorKind::ChargeNotAtEnd)),
+            Some(c) if c == ')' => return Err(self.error(self.pos, FormulaErrorKind::UnmatchedClose(c))),
+            Some(c) => return Err(self.error(self.pos, FormulaErrorKind::UnexpectedChar(c))),
+        }
+
+        let parsed = ParsedFormula { terms: connectivity.terms, formula: self.chars[..formula_end].iter().collect(), charge, fragments: 1 };
+        Ok(CondensedFormula { parsed, bonds: connectivity.bonds })
+    }
+
+    // reads units until one cannot start, bonding the first chain atom to `parent`;
+    // returns the atom the chain hangs off its parent by and the last atom of the chain
+    fn chain(&mut self, connectivity: &mut Connectivity, parent: Option<usize>) -> Result<Option<(usize, usize)>, FormulaError> {
+        let mut first: Option<usize> = None;
+        let mut current: Option<usize> = None;
+        // atoms waiting for the first chain atom
+        let mut pending: Vec<usize> = vec![];
+        // chain atoms of the last symbol read, which the hydrogens after it fill up in order, so `C2H5` is CH3CH2
+        let mut run: Vec<usize> = vec![];
+
+        while let Some(c) = self.peek() {
+            if c.is_ascii_uppercase() {
+                let symbol = self.symbol();
+                let count = self.count()?;
+                if let Some(carbon) = current.filter(|_| symbol == "O").filter(|&atom| self.takes_carbonyl(connectivity, atom, count)) {
+                    let oxygen = connectivity.add("O");
+                    connectivity.bonds.push((carbon, oxygen));
+                    if count == Some(2) {
+                        let hydroxyl = connectivity.add("O");
+                        connectivity.bonds.push((carbon, hydroxyl));
+                        current = Some(hydroxyl);
+                        run = vec![hydroxyl];
+                    }
+                    continue;
+                }
+                if symbol != "H" {
+                    run.clear();
+                }
+                // counted carbons are a chain (`C2H5`), other counted atoms substituents on the chain atom (`CCl3`)
+                let extends_chain = count.is_none() || symbol == "C";
+                for _ in 0..count.unwrap_or(1) {
+                    let atom = connectivity.add(&symbol);
+                    match current {
+                        Some(chain_atom) if symbol == "H" => {
+                            let target = run.iter().copied().find(|&a| connectivity.has_room(a)).unwrap_or(chain_atom);
+                            connectivity.bonds.push((target, atom));
+                        }
+                        Some(chain_atom) if !extends_chain => connectivity.bonds.push((chain_atom, atom)),
+                        None if symbol == "H" => pending.push(atom),
+                        _ => {
+                            if let Some(previous) = current.or(parent) {
+                                connectivity.bonds.push((previous, atom));
+                            }
+                            connectivity.bonds.extend(pending.drain(..).map(|waiting| (atom, waiting)));
+                            first.get_or_insert(atom);
+                            current = Some(atom);
+                            run.push(atom);
+                        }
+                    }
+                }
+            } else if c == '(' && !self.at_charge() {
+                let open_pos = self.pos;
+                self.pos += 1;
+                let inner_start = self.pos;
+                let mut copies = 0;
+                let mut after_count;
+                // a repeated unit with room left on its last atom, like the CH2 of `CH3(CH2)4CH3`, continues the
+                // chain with each copy; one without, like the CH3 of `CH(CH3)2`, is a branch repeated on one atom
+                let mut parent = current;
+                let mut extended = None;
+                loop {
+                    let terms_before = connectivity.terms.len();
+                    let branch = self.chain(connectivity, parent)?;
+                    match self.peek() {
+                        None => return Err(self.error(open_pos, FormulaErrorKind::UnclosedGroup('('))),
+                        Some(')') if connectivity.terms.len() == terms_before =>
+                            return Err(self.error(open_pos, FormulaErrorKind::EmptyGroup)),
+                        Some(')') => self.pos += 1,
+                        Some(c) => return Err(self.error(self.pos, FormulaErrorKind::UnexpectedChar(c))),
+                    }
+                    if current.is_none() {
+                        pending.extend(branch.map(|(first, _)| first));
+                    }
+                    copies += 1;
+                    let multiplier = if self.at_charge() { 1 } else { self.count()?.unwrap_or(1) };
+                    after_count = self.pos;
+                    if copies == 1 && multiplier > 1 && current.is_some() {
+                        extended = branch.map(|(_, last)| last).filter(|&last| connectivity.has_room(last));
+                    }
+                    if extended.is_some() {
+                        parent = branch.map(|(_, last)| last);
+                        extended = parent;
+                    }
+                    if copies >= multiplier {
+                        break;
+                    }
+                    self.pos = inner_start;
+                }
+                self.pos = after_count;
+                if let Some(last) = extended {
+                    current = Some(last);
+                    run = vec![last];
+                }
+            } else {
+                break;
+            }
+        }
+
+        match (first, parent) {
+            (Some(first), _) => Ok(Some((first, current.unwrap_or(first)))),
+            // a branch of hydrogens only
+            (None, Some(parent)) => {
+                connectivity.bonds.extend(pending.iter().map(|&waiting| (parent, waiting)));
+                Ok(None)
+            }
+            // nothing but hydrogens, which can only pair up as H2
+            (None, None) => {
+                if let [a, b] = pending[..] {
+                    connectivity.bonds.push((a, b));
+                }
+                Ok(pending.first().map(|&h| (h, h)))
+            }
+        }
+    }
+
+    // whether the oxygen just read (`count` of them) after chain atom `carbon` is a carbonyl group on it: the
+    // carbon needs room for the double bond, and for the single bond to the second oxygen of `CO2`; a lone oxygen
+    // with a hydrogen after it is a hydroxyl group, as in `CH3CH2OH`
+    fn takes_carbonyl(&self, connectivity: &Connectivity, carbon: usize, count: Option<u32>) -> bool {
+        if connectivity.terms[carbon].symbol != "C" {
+            return false;
+        }
+        // oxygens bonded to nothing else are carbonyl oxygens already, which take two of the carbon's bonds
+        let used = connectivity.bonds.iter()
+            .filter_map(|&(a, b)| if a == carbon { Some(b) } else if b == carbon { Some(a) } else { None })
+            .map(|other| if connectivity.terms[other].symbol == "O" && connectivity.degree(other) == 1 { 2 } else { 1 })
+            .sum::<usize>();
+        let hydrogen_next = self.peek() == Some('H') && !self.chars.get(self.pos + 1).is_some_and(|c| c.is_ascii_lowercase());
+        match count {
+            None => used + 2 <= 4 && !hydrogen_next,
+            Some(2) => used + 3 <= 4,
+            _ => false,
+        }
+    }
+
+    fn multiply(&self, terms: &mut [FormulaTerm], multiplier: u32, position: usize) -> Result<(), FormulaError> {
+        for term in terms.iter_mut() {
+            term.count = term.count.checked_mul(multiplier)
+                .ok_or_else(|| self.error(position, FormulaErrorKind::CountOverflow))?;
+        }
+        Ok(())
+    }
+}
+
+// parses a chemical formula into its element terms, in order of appearance, and its charge if one is written;
+// groups are expanded, so `Ca(OH)2` gives Ca1 O2 H2 rather than merging repeated symbols
+pub fn parse_formula(input: &str) -> Result<ParsedFormula, FormulaError> {
+    Parser::new(input).formula()
+}
+
+// parses a condensed formula such as `CH3CH2OH` into its atoms and the bonds between them
+pub fn parse_condensed(input: &str) -> Result<CondensedFormula, FormulaError> {
+    Parser::new(input).condensed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // terms as `C12`, `H22`, ... with the formula text and the charge
+    fn terms(input: &str) -> (Vec<String>, String, Option<i32>) {
+        let parsed = parse_formula(input).unwrap();
+        (parsed.terms.iter().map(|t| format!("{}{}", t.symbol, t.count)).collect(), parsed.formula, parsed.charge)
+    }
+
+    fn error_kind(input: &str) -> (FormulaErrorKind, usize) {
+        let error = parse_formula(input).unwrap_err();
+        (error.kind, error.position)
+    }
+
+    #[test]
+    fn counts_groups_and_hydrates() {
+        assert_eq!(terms("C12H22O11").0, ["C12", "H22", "O11"]);
+        assert_eq!(terms("Ca(OH)2").0, ["Ca1", "O2", "H2"]);
+        assert_eq!(terms("K[Fe(CN)6]").0, ["K1", "Fe1", "C6", "N6"]);
+        assert_eq!(terms("CuSO4·5H2O").0, ["Cu1", "S1", "O4", "H10", "O5"]);
+        assert_eq!(parse_formula("CuSO4·5H2O").unwrap().fragments, 2);
+        assert_eq!(parse_formula("C12H22O11").unwrap().fragments, 1);
+        assert_eq!(terms("CuSO4.5H2O").0, terms("CuSO4*5H2O").0);
+        assert_eq!(terms("CuSO4•5H2O").0, terms("CuSO4*5H2O").0);
+    }
+
+    #[test]
+    fn charges() {
+        assert_eq!(terms("SO4^2-"), (vec!["S1".to_string(), "O4".to_string()], "SO4".to_string(), Some(-2)));
+        assert_eq!(terms("NH4+").2, Some(1));
+        assert_eq!(terms("NH4+").0, ["N1", "H4"]);
+        assert_eq!(terms("[PO4]3-"), (vec!["P1".to_string(), "O4".to_string()], "[PO4]".to_string(), Some(-3)));
+        assert_eq!(terms("CO3(2-)").2, Some(-2));
+        assert_eq!(terms("CO3-2").2, Some(-2));
+        assert_eq!(terms("CO3−2").2, Some(-2));
+        assert_eq!(terms("Fe++").2, Some(2));
+        assert_eq!(terms("Ca(OH)2+").2, Some(1));
+        assert_eq!(terms("H2O").2, None);
+    }
+
+    // digits before a sign stay a count unless they follow ']', so SO42- is S O42 with a charge of -1
+    #[test]
+    fn digits_before_a_sign_are_a_count() {
+        assert_eq!(terms("SO42-"), (vec!["S1".to_string(), "O42".to_string()], "SO42".to_string(), Some(-1)));
+        assert_eq!(terms("Fe3+").0, ["Fe3"]);
+        assert_eq!(terms("[SO4]2-").2, Some(-2));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error_kind(""), (FormulaErrorKind::Empty, 0));
+        assert_eq!(error_kind("C%"), (FormulaErrorKind::UnexpectedChar('%'), 1));
+        assert_eq!(error_kind("C."), (FormulaErrorKind::UnexpectedEnd, 2));
+        assert_eq!(error_kind("SO4^"), (FormulaErrorKind::UnexpectedEnd, 4));
+        assert_eq!(error_kind("Ca(OH"), (FormulaErrorKind::UnclosedGroup('('), 2));
+        assert_eq!(error_kind("CO3(2-"), (FormulaErrorKind::UnclosedGroup('('), 3));
+        assert_eq!(error_kind("Ca(OH]"), (FormulaErrorKind::MismatchedClose { expected: ')', found: ']' }, 5));
+        assert_eq!(error_kind("CaOH)"), (FormulaErrorKind::UnmatchedClose(')'), 4));
+        assert_eq!(error_kind("Ca()"), (FormulaErrorKind::EmptyGroup, 2));
+        assert_eq!(error_kind("H0"), (FormulaErrorKind::ZeroCount, 1));
+        assert_eq!(error_kind("C99999999999"), (FormulaErrorKind::CountOverflow, 1));
+        assert_eq!(error_kind("SO4^2"), (FormulaErrorKind::MissingChargeSign, 5));
+        assert_eq!(error_kind("SO4-C"), (FormulaErrorKind::ChargeNotAtEnd, 4));
+    }
+
+    #[test]
+    fn errors_point_at_the_position() {
+        assert_eq!(parse_formula("Ca(OH]").unwrap_err().to_string(), "expected ')' but found ']' at position 5\n  Ca(OH]\n       ^");
+        assert_eq!(parse_formula("").unwrap_err().to_string(), "empty formula");
+        // positions count characters, not bytes
+        assert_eq!(parse_formula("CuSO4·5H2O%").unwrap_err().to_string(), "unexpected character '%' at position 10\n  CuSO4·5H2O%\n            ^");
+    }
+
+    #[test]
+    fn condensed_errors() {
+        assert_eq!(parse_condensed("CH3(").unwrap_err().kind, FormulaErrorKind::UnclosedGroup('('));
+        assert_eq!(parse_condensed("CH3()").unwrap_err().kind, FormulaErrorKind::EmptyGroup);
+        assert_eq!(parse_condensed("CH3)").unwrap_err().kind, FormulaErrorKind::UnmatchedClose(')'));
+        assert_eq!(parse_condensed("CH3COO-H").unwrap_err().kind, FormulaErrorKind::ChargeNotAtEnd);
+    }
+
+    // bonds of a condensed formula as atom labels such as `C0-O1`, numbered by position in the formula
+    fn condensed_bonds(input: &str) -> Vec<String> {
+        let condensed = parse_condensed(input).unwrap();
+        let label = |i: usize| format!("{}{}", condensed.parsed.terms[i].symbol, i);
+        condensed.bonds.iter().map(|&(a, b)| format!("{}-{}", label(a), label(b))).collect()
+    }
+
+    #[test]
+    fn condensed_chains_and_branches() {
+        assert_eq!(condensed_bonds("CH3CH2OH"), ["C0-H1", "C0-H2", "C0-H3", "C0-C4", "C4-H5", "C4-H6", "C4-O7", "O7-H8"]);
+        assert_eq!(condensed_bonds("CH3OCH3"), ["C0-H1", "C0-H2", "C0-H3", "C0-O4", "O4-C5", "C5-H6", "C5-H7", "C5-H8"]);
+        assert_eq!(condensed_bonds("HOCH3"), ["O1-H0", "O1-C2", "C2-H3", "C2-H4", "C2-H5"]);
+        assert_eq!(condensed_bonds("CH3CH(CH3)OH"), ["C0-H1", "C0-H2", "C0-H3", "C0-C4", "C4-H5", "C4-C6", "C6-H7", "C6-H8",
+            "C6-H9", "C4-O10", "O10-H11"]);
+        assert_eq!(condensed_bonds("CCl3"), ["C0-Cl1", "C0-Cl2", "C0-Cl3"]);
+    }
+
+    #[test]
+    fn repeated_units_extend_the_chain() {
+        assert_eq!(condensed_bonds("CH3(CH2)4CH3"), ["C0-H1", "C0-H2", "C0-H3", "C0-C4", "C4-H5", "C4-H6", "C4-C7", "C7-H8",
+            "C7-H9", "C7-C10", "C10-H11", "C10-H12", "C10-C13", "C13-H14", "C13-H15", "C13-C16", "C16-H17", "C16-H18",
+            "C16-H19"]);
+        assert_eq!(condensed_bonds("C2H5OH"), ["C0-C1", "C0-H2", "C0-H3", "C0-H4", "C1-H5", "C1-H6", "C1-O7", "O7-H8"]);
+        assert_eq!(condensed_bonds("HO(CH2)2OH"), ["O1-H0", "O1-C2", "C2-H3", "C2-H4", "C2-C5", "C5-H6", "C5-H7", "C5-O8",
+            "O8-H9"]);
+        // a full repeated unit stays a branch on one atom
+        assert_eq!(condensed_bonds("(CH3)3CH"), ["C0-H1", "C0-H2", "C0-H3", "C4-H5", "C4-H6", "C4-H7", "C8-H9", "C8-H10",
+            "C8-H11", "C12-C0", "C12-C4", "C12-C8", "C12-H13"]);
+    }
+
+    #[test]
+    fn carbonyl_oxygens_hang_off_their_carbon() {
+        assert_eq!(condensed_bonds("CH3COOH"), ["C0-H1", "C0-H2", "C0-H3", "C0-C4", "C4-O5", "C4-O6", "O6-H7"]);
+        assert_eq!(condensed_bonds("CH3CO2H"), ["C0-H1", "C0-H2", "C0-H3", "C0-C4", "C4-O5", "C4-O6", "O6-H7"]);
+        assert_eq!(condensed_bonds("CH3COCH3"), ["C0-H1", "C0-H2", "C0-H3", "C0-C4", "C4-O5", "C4-C6", "C6-H7", "C6-H8", "C6-H9"]);
+        assert_eq!(condensed_bonds("HCOOH"), ["C1-H0", "C1-O2", "C1-O3", "O3-H4"]);
+        assert_eq!(condensed_bonds("CH3CHO"), ["C0-H1", "C0-H2", "C0-H3", "C0-C4", "C4-H5", "C4-O6"]);
+        assert_eq!(condensed_bonds("CH3COOCH3"), ["C0-H1", "C0-H2", "C0-H3", "C0-C4", "C4-O5", "C4-O6", "O6-C7", "C7-H8",
+            "C7-H9", "C7-H10"]);
+        assert_eq!(condensed_bonds("CH3COCl"), ["C0-H1", "C0-H2", "C0-H3", "C0-C4", "C4-O5", "C4-Cl6"]);
+    }
+}
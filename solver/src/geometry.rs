@@ -0,0 +1,90 @@
+use schemars::JsonSchema;
+use serde::{Serialize, Deserialize};
+
+// VSEPR shapes, named like the Geometry enum of the display; the variant names double as the strings in the json
+// output
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum Geometry {
+    // 0 groups
+    Single,
+    // 1 group
+    Linear2,
+    // 2 groups
+    Linear,
+    // 3 groups
+    TrigonalPlanar,
+    Bent1Lone,
+    // 4 groups
+    Tetrahedral,
+    TrigonalPyramidal,
+    Bent2Lone,
+    // 5 groups
+    TrigonalBipyramidal,
+    Seesaw,
+    TShape,
+    Linear3Lone,
+    // 6 groups
+    Octahedral,
+    SquarePyramidal,
+    SquarePlanar,
+    // 7 groups
+    PentagonalBipyramidal,
+    PentagonalPyramidal,
+    PentagonalPlanar,
+    // 8 groups
+    SquareAntiprismatic,
+}
+
+impl Geometry {
+    // arrangement of all electron domains (bonded atoms and lone pairs) around an atom
+    pub fn electron_domain(steric_number: usize) -> Option<Geometry> {
+        match steric_number {
+            0 => Some(Geometry::Single),
+            1 => Some(Geometry::Linear2),
+            2 => Some(Geometry::Linear),
+            3 => Some(Geometry::TrigonalPlanar),
+            4 => Some(Geometry::Tetrahedral),
+            5 => Some(Geometry::TrigonalBipyramidal),
+            6 => Some(Geometry::Octahedral),
+            7 => Some(Geometry::PentagonalBipyramidal),
+            8 => Some(Geometry::SquareAntiprismatic),
+            _ => None,
+        }
+    }
+
+    // arrangement of the bonded atoms alone, with the lone pairs where VSEPR puts them (equatorial for a trigonal
+    // bipyramid, trans for an octahedron, axial for a pentagonal bipyramid); None for shapes without a name here
+    pub fn molecular(bonded: usize, lone_pairs: usize) -> Option<Geometry> {
+        match (bonded, lone_pairs) {
+            (0, _) => Some(Geometry::Single),
+            (1, _) => Some(Geometry::Linear2),
+            (2, 0) => Some(Geometry::Linear),
+            (3, 0) => Some(Geometry::TrigonalPlanar),
+            (2, 1) => Some(Geometry::Bent1Lone),
+            (4, 0) => Some(Geometry::Tetrahedral),
+            (3, 1) => Some(Geometry::TrigonalPyramidal),
+            (2, 2) => Some(Geometry::Bent2Lone),
+            (5, 0) => Some(Geometry::TrigonalBipyramidal),
+            (4, 1) => Some(Geometry::Seesaw),
+            (3, 2) | (3, 3) => Some(Geometry::TShape),
+            (2, 3) => Some(Geometry::Linear3Lone),
+            (6, 0) => Some(Geometry::Octahedral),
+            (5, 1) => Some(Geometry::SquarePyramidal),
+            (4, 2) => Some(Geometry::SquarePlanar),
+            (2, 4) => Some(Geometry::Linear),
+            (7, 0) => Some(Geometry::PentagonalBipyramidal),
+            (6, 1) => Some(Geometry::PentagonalPyramidal),
+            (5, 2) => Some(Geometry::PentagonalPlanar),
+            (8, 0) => Some(Geometry::SquareAntiprismatic),
+            _ => None,
+        }
+    }
+}
+
+// AXE notation, e.g. AX4 for methane's carbon and AX2E2 for water's oxygen
+pub fn axe_notation(bonded: usize, lone_pairs: usize) -> String {
+    match lone_pairs {
+        0 => format!("AX{}", bonded),
+        _ => format!("AX{}E{}", bonded, lone_pairs),
+    }
+}
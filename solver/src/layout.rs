@@ -0,0 +1,69 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+// bond length used for flat drawings, the usual one for 2D molfiles
+const BOND_LENGTH: f64 = 1.5;
+
+// atoms closer than this to an already placed one are turned away from it
+const MIN_DISTANCE: f64 = 0.75;
+
+// flat coordinates (z = 0) for atoms joined by bonds with the given orders: a breadth first walk puts each atom's
+// other neighbors evenly around it, away from the atom it was reached from; chains zigzag at 120 degrees unless
+// the atom is linear (a triple bond or two double bonds), and bonds closing a ring are drawn wherever their
+// atoms ended up; an atom that would land on top of another is turned around its neighbor in 15 degree steps
+// until it is clear, and disconnected pieces are set side by side
+pub fn layout_2d(count: usize, bonds: &[(usize, usize, u32)]) -> Vec<[f64; 3]> {
+    let mut neighbors: Vec<Vec<(usize, u32)>> = vec![vec![]; count];
+    for &(a, b, order) in bonds {
+        neighbors[a].push((b, order));
+        neighbors[b].push((a, order));
+    }
+
+    let mut positions: Vec<Option<[f64; 3]>> = vec![None; count];
+    let mut offset = 0.0;
+    for start in 0..count {
+        if positions[start].is_some() {
+            continue;
+        }
+        positions[start] = Some([offset, 0.0, 0.0]);
+        // atom, angle pointing back to where it was reached from and which way its chain bends
+        let mut queue = VecDeque::from([(start, None::<f64>, 1.0)]);
+        let mut piece = vec![start];
+        while let Some((atom, back, bend)) = queue.pop_front() {
+            let [x, y, _] = positions[atom].unwrap_or_default();
+            let degree = neighbors[atom].len();
+            let linear = neighbors[atom].iter().any(|&(_, order)| order == 3)
+                || neighbors[atom].iter().filter(|&&(_, order)| order == 2).count() >= 2;
+            let step = match (degree, back) {
+                (2, Some(_)) if !linear => 2.0 * PI / 3.0,
+                _ => 2.0 * PI / degree.max(1) as f64,
+            };
+
+            let mut k = 0;
+            for &(next, _) in neighbors[atom].iter() {
+                if positions[next].is_some() {
+                    continue;
+                }
+                k += 1;
+                let planned = match back {
+                    Some(back) => back + bend * step * k as f64,
+                    None => step * (k - 1) as f64,
+                };
+                let at = |angle: f64| [x + BOND_LENGTH * angle.cos(), y + BOND_LENGTH * angle.sin(), 0.0];
+                let clear = |[px, py, _]: [f64; 3]| positions.iter().flatten()
+                    .all(|&[qx, qy, _]| (px - qx).hypot(py - qy) >= MIN_DISTANCE);
+                let angle = (0..24)
+                    .map(|turn: i32| planned + (if turn % 2 == 0 { 1.0 } else { -1.0 }) * ((turn + 1) / 2) as f64 * PI / 12.0)
+                    .find(|&angle| clear(at(angle)))
+                    .unwrap_or(planned);
+                positions[next] = Some(at(angle));
+                queue.push_back((next, Some(angle + PI), -bend));
+                piece.push(next);
+            }
+        }
+        let right = piece.iter().filter_map(|&i| positions[i]).map(|[x, _, _]| x).fold(offset, f64::max);
+        offset = right + 2.0 * BOND_LENGTH;
+    }
+
+    positions.into_iter().map(Option::unwrap_or_default).collect()
+}
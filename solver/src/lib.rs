@@ -0,0 +1,49 @@
+// Lewis structures of molecules written as formulas, condensed formulas or SMILES: `parse` the input, `solve` it
+// and read the atoms, bonds, lone pairs, hybridization and formal charges off the resulting Model, e.g.
+//
+//     let table = ElementTable::embedded()?;
+//     let model = solve(&parse("SO4", Some(-2), InputFormat::Formula, &table)?)?;
+//     for atom in model.atoms() {
+//         println!("{}{} {:?} {}", atom.symbol(), atom.id(), atom.hybridization(), atom.formal_charge());
+//     }
+
+pub mod element;
+pub mod error;
+pub mod formula;
+pub mod geometry;
+pub mod molfile;
+pub mod smiles;
+pub mod solve;
+pub mod validate;
+mod coordinates;
+mod diagram;
+mod layout;
+mod svg;
+
+pub use crate::element::{Element, ElementTable};
+pub use crate::error::LewisError;
+pub use crate::geometry::Geometry;
+pub use crate::molfile::MolfileVersion;
+pub use crate::solve::{Atom, AtomId, Bond, Hybridization, InputFormat, Model, ParsedMolecule, SCHEMA_VERSION};
+pub use crate::validate::Violation;
+
+// reads a compound written in `format`; the charge can be given here, written in the compound (`SO4^2-`) or both
+// if they agree, and is 0 otherwise
+pub fn parse(compound: &str, charge: Option<i32>, format: InputFormat, table: &ElementTable) -> Result<ParsedMolecule, LewisError> {
+    solve::parse_compound(compound, charge, format, table)
+}
+
+// the best Lewis structure of the molecule
+pub fn solve(molecule: &ParsedMolecule) -> Result<Model, LewisError> {
+    solve::build_model(molecule)
+}
+
+// JSON Schema of what Model::to_json and Model::write_to_json produce
+pub fn json_schema() -> String {
+    solve::json_schema()
+}
+
+// every Lewis structure of the molecule that scores as well as the one `solve` returns
+pub fn solve_resonance(molecule: &ParsedMolecule) -> Result<Vec<Model>, LewisError> {
+    solve::build_resonance_models(molecule)
+}
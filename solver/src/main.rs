@@ -1,18 +1,251 @@
-mod solve;
-
 use std::env;
-use crate::solve::parse_input;
-use crate::solve::build_model;
+use std::process::ExitCode;
+use lewis_structure_solver::element::ElementTable;
+use lewis_structure_solver::molfile::MolfileVersion;
+use lewis_structure_solver::solve::{parse_input, InputFormat};
+use lewis_structure_solver::solve::build_resonance_models;
+use lewis_structure_solver::solve::write_sdf;
+use lewis_structure_solver::solve::Model;
+use lewis_structure_solver::solve::json_schema;
 
-pub fn main() {
-    let args: Vec<String> = env::args().collect();
-    let input_molecule = parse_input(&args);
+struct Options {
+    // program name, formula and optional charge
+    args: Vec<String>,
+    // saved json model to show instead of solving a formula
+    from_json: Option<String>,
+    data_path: Option<String>,
+    resonance: bool,
+    format: InputFormat,
+    // explicit skeleton, e.g. `C0-C1,C1-O0`
+    bonds: Option<String>,
+    // also write out/{name}.mol, or out/{name}.sdf with every resonance structure
+    molfile: bool,
+    sdf: bool,
+    // also write out/{name}.xyz with 3D coordinates
+    xyz: bool,
+    // dump the atoms and their bonds instead of drawing them
+    debug: bool,
+    // print the JSON Schema of the json output and exit
+    json_schema: bool,
+    // validate every structure and fail if one is inconsistent
+    check: bool,
+    // how the diagram is drawn
+    output: OutputFormat,
+    // wedges and dashes in svg diagrams
+    wedges: bool,
+    molfile_version: MolfileVersion,
+}
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    // printed to the terminal
+    Ascii,
+    // written to out/{name}.svg
+    Svg,
+}
+
+pub fn main() -> ExitCode {
+    let options = match parse_flags(env::args().collect()) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if options.json_schema {
+        print!("{}", json_schema());
+        return ExitCode::SUCCESS;
+    }
+    // a custom element table can be given with --data or LEWIS_DATA, otherwise the embedded one is used
+    let table = match options.data_path.clone().or_else(|| env::var("LEWIS_DATA").ok()) {
+        Some(path) => ElementTable::read_csv(&path),
+        None => ElementTable::embedded(),
+    };
+    let table = match table {
+        Ok(table) => table,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
     use std::time::Instant;
     let now = Instant::now();
-    let model_molecule = build_model(&input_molecule);
+    // the first resonance structure is the one build_model returns; the others average the bond orders in the json
+    let resonance_models = match &options.from_json {
+        Some(path) => Model::read_from_json(path, &table).map(|model| vec![model]),
+        None => parse_input(&options.args, options.format, &table)
+            .and_then(|input_molecule| match &options.bonds {
+                Some(bonds) => input_molecule.with_bonds(bonds),
+                None => Ok(input_molecule),
+            })
+            .and_then(|input_molecule| build_resonance_models(&input_molecule)),
+    };
+    let resonance_models = match resonance_models {
+        Ok(resonance_models) => resonance_models,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
     let elapsed = now.elapsed();
-    model_molecule.print_model();
-    let _ = model_molecule.write_to_json(format!("out/{}_{}.json", args[1], args[2]));
+    let model_molecule = &resonance_models[0];
+    if options.resonance {
+        for (i, resonance_model) in resonance_models.iter().enumerate() {
+            println!("Resonance structure {} of {}:\n", i + 1, resonance_models.len());
+            print_structure(resonance_model, &options);
+            println!("SMILES: {}\n", resonance_model.to_smiles());
+        }
+    } else {
+        print_structure(model_molecule, &options);
+        println!("SMILES: {}\n", model_molecule.to_smiles());
+    }
+    if options.check {
+        let checked = if options.resonance { &resonance_models[..] } else { &resonance_models[..1] };
+        let mut valid = true;
+        for (i, model) in checked.iter().enumerate() {
+            for violation in model.validate() {
+                match checked.len() {
+                    1 => eprintln!("check failed: {}", violation),
+                    _ => eprintln!("check failed in resonance structure {}: {}", i + 1, violation),
+                }
+                valid = false;
+            }
+        }
+        if !valid {
+            return ExitCode::FAILURE;
+        }
+        println!("Check: ok");
+    }
+    // a model read from json is not written back, which would lose its averaged bond orders
+    if options.from_json.is_none() {
+        let _ = model_molecule.write_to_json(format!("out/{}.json", model_molecule.name()), &resonance_models);
+    }
+    if options.output == OutputFormat::Svg {
+        // one image per resonance structure when they are all printed
+        let images = if options.resonance {
+            resonance_models.iter().enumerate()
+                .map(|(i, model)| (format!("out/{}_{}.svg", model_molecule.name(), i + 1), model))
+                .collect::<Vec<_>>()
+        } else {
+            vec![(format!("out/{}.svg", model_molecule.name()), model_molecule)]
+        };
+        for (path, model) in images {
+            if let Err(e) = model.write_svg(path.clone(), options.wedges) {
+                eprintln!("error: cannot write {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if options.molfile {
+        let path = format!("out/{}.mol", model_molecule.name());
+        if let Err(e) = model_molecule.write_molfile(path.clone(), options.molfile_version) {
+            eprintln!("error: cannot write {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    if options.xyz {
+        let path = format!("out/{}.xyz", model_molecule.name());
+        if let Err(e) = model_molecule.write_xyz(path.clone()) {
+            eprintln!("error: cannot write {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    if options.sdf {
+        let path = format!("out/{}.sdf", model_molecule.name());
+        if let Err(e) = write_sdf(&resonance_models, path.clone(), options.molfile_version) {
+            eprintln!("error: cannot write {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    }
     println!("Elapsed: {:.2?}", elapsed);
+    ExitCode::SUCCESS
+}
+
+// the shape around the central atom, e.g. `Tetrahedral`, atoms short of an octet or with an unpaired electron and
+// the Lewis diagram
+fn print_structure(model: &Model, options: &Options) {
+    if let Some(geometry) = model.geometry() {
+        println!("{:?}", geometry);
+    }
+    let deficient = model.electron_deficient().into_iter()
+        .map(|id| format!("{}{} has {} electrons", model.atom(id).symbol(), model.atom(id).id(), model.shell_electrons(id)))
+        .collect::<Vec<_>>();
+    if !deficient.is_empty() {
+        println!("Electron-deficient: {}", deficient.join(", "));
+    }
+    for id in model.radicals() {
+        println!("Radical: {}{} has an unpaired electron", model.atom(id).symbol(), model.atom(id).id());
+    }
+    if options.debug {
+        model.print_model();
+    } else if options.output == OutputFormat::Ascii {
+        println!("{}", model.render_ascii());
+    }
+}
+
+// takes the `--` flags out of the arguments, leaving the positional ones
+fn parse_flags(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options { args: vec![], from_json: None, data_path: None, resonance: false, format: InputFormat::Formula, bonds: None,
+        molfile: false, sdf: false, xyz: false, debug: false, json_schema: false, check: false, output: OutputFormat::Ascii, wedges: false, molfile_version: MolfileVersion::V2000 };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--data" {
+            options.data_path = Some(args.next().ok_or("--data needs a path to a csv file")?);
+        } else if let Some(path) = arg.strip_prefix("--data=") {
+            options.data_path = Some(path.to_string());
+        } else if arg == "--resonance" {
+            options.resonance = true;
+        } else if arg == "--condensed" {
+            options.format = InputFormat::Condensed;
+        } else if arg == "--molfile" {
+            options.molfile = true;
+        } else if arg == "--sdf" {
+            options.sdf = true;
+        } else if arg == "--xyz" {
+            options.xyz = true;
+        } else if arg == "--format" {
+            options.output = parse_output_format(&args.next().ok_or("--format needs ascii or svg")?)?;
+        } else if let Some(format) = arg.strip_prefix("--format=") {
+            options.output = parse_output_format(format)?;
+        } else if arg == "--wedges" {
+            options.wedges = true;
+        } else if arg == "--from-json" {
+            options.from_json = Some(args.next().ok_or("--from-json needs a path to a json file")?);
+        } else if let Some(path) = arg.strip_prefix("--from-json=") {
+            options.from_json = Some(path.to_string());
+        } else if arg == "--check" {
+            options.check = true;
+        } else if arg == "--json-schema" {
+            options.json_schema = true;
+        } else if arg == "--debug" {
+            options.debug = true;
+        } else if arg == "--v3000" {
+            options.molfile_version = MolfileVersion::V3000;
+        } else if arg == "--smiles" {
+            options.format = InputFormat::Smiles;
+        } else if arg == "--bonds" {
+            options.bonds = Some(args.next().ok_or("--bonds needs a list of bonds such as C0-C1,C1-O0")?);
+        } else if let Some(bonds) = arg.strip_prefix("--bonds=") {
+            options.bonds = Some(bonds.to_string());
+        } else if arg.starts_with("--") {
+            return Err(format!("unknown flag {}", arg));
+        } else {
+            options.args.push(arg);
+        }
+    }
+    if options.bonds.is_some() && options.format != InputFormat::Formula {
+        return Err("--bonds cannot be combined with --condensed or --smiles, which already fix the bonds".to_string());
+    }
+    if options.from_json.is_some() && (options.args.len() > 1 || options.bonds.is_some() || options.format != InputFormat::Formula) {
+        return Err("--from-json reads a solved model and cannot be combined with a compound to solve".to_string());
+    }
+    Ok(options)
+}
+
+fn parse_output_format(format: &str) -> Result<OutputFormat, String> {
+    match format {
+        "ascii" => Ok(OutputFormat::Ascii),
+        "svg" => Ok(OutputFormat::Svg),
+        _ => Err(format!("unknown format {}, expected ascii or svg", format)),
+    }
 }
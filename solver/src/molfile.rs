@@ -0,0 +1,111 @@
+// MDL molfile (V2000 and V3000) blocks, see the CTfile formats specification
+
+// largest atom or bond count a V2000 counts line can hold
+const V2000_MAX_COUNT: usize = 999;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MolfileVersion {
+    V2000,
+    V3000,
+}
+
+// one atom of a molfile, by symbol, formal charge, whether it has an unpaired electron and position in angstroms
+#[derive(Debug, Clone, PartialEq)]
+pub struct MolAtom {
+    pub symbol: String,
+    pub charge: i32,
+    pub radical: bool,
+    pub position: [f64; 3],
+}
+
+// radical code of an atom with one unpaired electron
+const DOUBLET: u32 = 2;
+
+// V2000 keeps charges from -3 to +3 in the atom block, all others only in `M  CHG` lines
+fn v2000_charge_code(charge: i32) -> u32 {
+    match charge {
+        3 => 1,
+        2 => 2,
+        1 => 3,
+        -1 => 5,
+        -2 => 6,
+        -3 => 7,
+        _ => 0,
+    }
+}
+
+// header block of three lines: name, program and dimension, comment
+fn header(name: &str, dimension: &str) -> String {
+    format!("{}\n  lewis-structure-solver     {}\n\n", name, dimension)
+}
+
+// molfile of the atoms and bonds (atom indices from 0, bond orders 1 to 3); V2000 falls back to V3000 when there
+// are more atoms or bonds than it can count
+pub fn molfile_block(name: &str, atoms: &[MolAtom], bonds: &[(usize, usize, u32)], dimension: &str, version: MolfileVersion) -> String {
+    let too_large = atoms.len() > V2000_MAX_COUNT || bonds.len() > V2000_MAX_COUNT;
+    match version {
+        MolfileVersion::V2000 if !too_large => v2000_block(name, atoms, bonds, dimension),
+        _ => v3000_block(name, atoms, bonds, dimension),
+    }
+}
+
+fn v2000_block(name: &str, atoms: &[MolAtom], bonds: &[(usize, usize, u32)], dimension: &str) -> String {
+    let mut block = header(name, dimension);
+    block.push_str(&format!("{:>3}{:>3}  0  0  0  0  0  0  0  0999 V2000\n", atoms.len(), bonds.len()));
+    for atom in atoms {
+        let [x, y, z] = atom.position;
+        block.push_str(&format!("{:>10.4}{:>10.4}{:>10.4} {:<3} 0{:>3}  0  0  0  0  0  0  0  0  0  0\n",
+            x, y, z, atom.symbol, v2000_charge_code(atom.charge)));
+    }
+    for &(a, b, order) in bonds {
+        block.push_str(&format!("{:>3}{:>3}{:>3}  0  0  0  0\n", a + 1, b + 1, order));
+    }
+
+    // at most eight charges, and eight radicals, per line
+    let charged = atoms.iter().enumerate().filter(|(_, atom)| atom.charge != 0).collect::<Vec<_>>();
+    for line in charged.chunks(8) {
+        block.push_str(&format!("M  CHG{:>3}", line.len()));
+        for (i, atom) in line {
+            block.push_str(&format!("{:>4}{:>4}", i + 1, atom.charge));
+        }
+        block.push('\n');
+    }
+    let radicals = atoms.iter().enumerate().filter(|(_, atom)| atom.radical).collect::<Vec<_>>();
+    for line in radicals.chunks(8) {
+        block.push_str(&format!("M  RAD{:>3}", line.len()));
+        for (i, _) in line {
+            block.push_str(&format!("{:>4}{:>4}", i + 1, DOUBLET));
+        }
+        block.push('\n');
+    }
+    block.push_str("M  END\n");
+    block
+}
+
+fn v3000_block(name: &str, atoms: &[MolAtom], bonds: &[(usize, usize, u32)], dimension: &str) -> String {
+    let mut block = header(name, dimension);
+    block.push_str("  0  0  0     0  0            999 V3000\n");
+    block.push_str("M  V30 BEGIN CTAB\n");
+    block.push_str(&format!("M  V30 COUNTS {} {} 0 0 0\n", atoms.len(), bonds.len()));
+    block.push_str("M  V30 BEGIN ATOM\n");
+    for (i, atom) in atoms.iter().enumerate() {
+        let [x, y, z] = atom.position;
+        block.push_str(&format!("M  V30 {} {} {:.4} {:.4} {:.4} 0", i + 1, atom.symbol, x, y, z));
+        if atom.charge != 0 {
+            block.push_str(&format!(" CHG={}", atom.charge));
+        }
+        if atom.radical {
+            block.push_str(&format!(" RAD={}", DOUBLET));
+        }
+        block.push('\n');
+    }
+    block.push_str("M  V30 END ATOM\n");
+    block.push_str("M  V30 BEGIN BOND\n");
+    for (i, &(a, b, order)) in bonds.iter().enumerate() {
+        block.push_str(&format!("M  V30 {} {} {} {}\n", i + 1, order, a + 1, b + 1));
+    }
+    block.push_str("M  V30 END BOND\n");
+    block.push_str("M  V30 END CTAB\n");
+    block.push_str("M  END\n");
+    block
+}
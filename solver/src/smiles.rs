@@ -0,0 +1,809 @@
+use std::collections::HashMap;
+use itertools::Itertools;
+use std::ops::RangeInclusive;
+use crate::error::{PositionedError, PositionedErrorKind};
+use crate::formula::{FormulaTerm, ParsedFormula};
+
+// subset of SMILES accepted by `parse_smiles`:
+//
+//   smiles  := atom (bond? (atom | ring) | '(' bond? atom ... ')' | '.' atom)*
+//   atom    := organic | '[' isotope? symbol chiral? hcount? charge? class? ']'
+//   organic := 'B' | 'C' | 'N' | 'O' | 'P' | 'S' | 'F' | 'Cl' | 'Br' | 'I' | 'b' | 'c' | 'n' | 'o' | 'p' | 's'
+//   bond    := '-' | '=' | '#' | ':' | '/' | '\'
+//   ring    := bond? (digit | '%' digit digit)
+//
+// atoms outside brackets get hydrogens up to their lowest usual valence that fits their bonds, bracket atoms
+// only the ones written in them; isotopes, chirality, classes and the '/' '\' directions are read but ignored
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmilesErrorKind {
+    Empty,
+    UnexpectedChar(char),
+    UnexpectedEnd,
+    UnclosedAtom,
+    UnclosedBranch,
+    UnmatchedClose,
+    UnclosedRing(u32),
+    RingBondMismatch(u32),
+    RingToSelf(u32),
+    CountOverflow,
+    // aromatic atoms that cannot all get a double bond, e.g. `c1cccc1`
+    NotKekulizable,
+}
+
+pub type SmilesError = PositionedError<SmilesErrorKind>;
+
+impl PositionedErrorKind for SmilesErrorKind {
+    fn describe(&self, position: usize) -> String {
+        match self {
+            SmilesErrorKind::Empty => "empty SMILES".to_string(),
+            SmilesErrorKind::UnexpectedChar(c) => format!("unexpected character '{}' at position {}", c, position),
+            SmilesErrorKind::UnexpectedEnd => format!("unexpected end of SMILES at position {}", position),
+            SmilesErrorKind::UnclosedAtom => format!("bracket atom opened at position {} is never closed", position),
+            SmilesErrorKind::UnclosedBranch => format!("branch opened at position {} is never closed", position),
+            SmilesErrorKind::UnmatchedClose => format!("')' at position {} has no matching '('", position),
+            SmilesErrorKind::UnclosedRing(n) => format!("ring bond {} opened at position {} is never closed", n, position),
+            SmilesErrorKind::RingBondMismatch(n) => format!("ring bond {} closed at position {} with a different bond than it was opened with", n, position),
+            SmilesErrorKind::RingToSelf(n) => format!("ring bond {} at position {} closes on the atom that opened it", n, position),
+            SmilesErrorKind::CountOverflow => format!("number too large at position {}", position),
+            SmilesErrorKind::NotKekulizable =>
+                format!("the aromatic atoms from position {} on cannot be given alternating double bonds", position),
+        }
+    }
+
+    fn has_position(&self) -> bool {
+        *self != SmilesErrorKind::Empty
+    }
+}
+
+// atoms of the SMILES (hydrogens last) as a formula with one term per atom, the bonds between them as term
+// indices and how many pi bonds each of those bonds may have (aromatic bonds are left to the solver)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedSmiles {
+    pub parsed: ParsedFormula,
+    pub bonds: Vec<(usize, usize)>,
+    pub pi_bonds: Vec<RangeInclusive<u32>>,
+    // pi bonds of each bond in one Kekulé structure, found by matching up the aromatic atoms that need a double bond
+    pub kekule: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bond {
+    Single,
+    Double,
+    Triple,
+    Aromatic,
+}
+
+impl Bond {
+    fn from_char(c: char) -> Option<Bond> {
+        match c {
+            '-' | '/' | '\\' => Some(Bond::Single),
+            '=' => Some(Bond::Double),
+            '#' => Some(Bond::Triple),
+            ':' => Some(Bond::Aromatic),
+            _ => None,
+        }
+    }
+
+    fn pi_bonds(self) -> RangeInclusive<u32> {
+        match self {
+            Bond::Single => 0..=0,
+            Bond::Double => 1..=1,
+            Bond::Triple => 2..=2,
+            Bond::Aromatic => 0..=1,
+        }
+    }
+
+    // aromatic bonds count as single here, aromatic atoms make up for it in `implicit_hydrogens`
+    fn order(self) -> u32 {
+        match self {
+            Bond::Double => 2,
+            Bond::Triple => 3,
+            Bond::Single | Bond::Aromatic => 1,
+        }
+    }
+}
+
+struct SmilesAtom {
+    symbol: String,
+    aromatic: bool,
+    // hydrogens written in a bracket atom, None outside brackets
+    hydrogens: Option<u32>,
+    charge: i32,
+    // where the atom starts in the input
+    position: usize,
+}
+
+// usual valences of the atoms that can be written outside brackets, lowest first
+fn usual_valences(symbol: &str) -> &'static [u32] {
+    match symbol {
+        "B" => &[3],
+        "C" => &[4],
+        "N" => &[3, 5],
+        "O" => &[2],
+        "P" => &[3, 5],
+        "S" => &[2, 4, 6],
+        _ => &[1],
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+    atoms: Vec<SmilesAtom>,
+    bonds: Vec<(usize, usize, Bond)>,
+    charge: Option<i32>,
+    // parts separated by '.', e.g. 2 for `[NH4+].[Cl-]`
+    fragments: usize,
+    // open ring bonds by number, with the atom, bond and position they were opened at
+    rings: HashMap<u32, (usize, Option<Bond>, usize)>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser { input, chars: input.chars().collect(), pos: 0, atoms: vec![], bonds: vec![], charge: None, fragments: 1, rings: HashMap::new() }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, position: usize, kind: SmilesErrorKind) -> SmilesError {
+        SmilesError { input: self.input.to_string(), position, kind }
+    }
+
+    fn unexpected(&self) -> SmilesError {
+        match self.peek() {
+            Some(c) => self.error(self.pos, SmilesErrorKind::UnexpectedChar(c)),
+            None => self.error(self.pos, SmilesErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn smiles(&mut self) -> Result<ParsedSmiles, SmilesError> {
+        if self.chars.is_empty() {
+            return Err(self.error(0, SmilesErrorKind::Empty));
+        }
+
+        // atoms branches were opened on, with the position of their '('
+        let mut branches: Vec<(usize, usize)> = vec![];
+        let mut previous: Option<usize> = None;
+        let mut bond: Option<Bond> = None;
+        while let Some(c) = self.peek() {
+            if c == '(' {
+                let Some(atom) = previous.filter(|_| bond.is_none()) else { return Err(self.unexpected()) };
+                branches.push((atom, self.pos));
+                self.pos += 1;
+            } else if c == ')' {
+                if bond.is_some() || (self.pos > 0 && self.chars[self.pos - 1] == '(') {
+                    return Err(self.unexpected());
+                }
+                let (atom, _) = branches.pop().ok_or_else(|| self.error(self.pos, SmilesErrorKind::UnmatchedClose))?;
+                previous = Some(atom);
+                self.pos += 1;
+            } else if let Some(b) = Bond::from_char(c) {
+                if previous.is_none() || bond.is_some() {
+                    return Err(self.unexpected());
+                }
+                bond = Some(b);
+                self.pos += 1;
+            } else if c == '.' {
+                if previous.is_none() || bond.is_some() || !branches.is_empty() {
+                    return Err(self.unexpected());
+                }
+                previous = None;
+                self.fragments += 1;
+                self.pos += 1;
+            } else if c.is_ascii_digit() || c == '%' {
+                let Some(atom) = previous else { return Err(self.unexpected()) };
+                self.ring(atom, bond.take())?;
+            } else {
+                let atom = if c == '[' { self.bracket_atom()? } else { self.organic_atom()? };
+                if let Some(p) = previous {
+                    let b = bond.take().unwrap_or(if self.atoms[p].aromatic && self.atoms[atom].aromatic { Bond::Aromatic } else { Bond::Single });
+                    self.bonds.push((p, atom, b));
+                }
+                previous = Some(atom);
+            }
+        }
+
+        if bond.is_some() {
+            return Err(self.unexpected());
+        }
+        if let Some(&(_, position)) = branches.last() {
+            return Err(self.error(position, SmilesErrorKind::UnclosedBranch));
+        }
+        if let Some((&number, &(_, _, position))) = self.rings.iter().min_by_key(|(_, ring)| ring.2) {
+            return Err(self.error(position, SmilesErrorKind::UnclosedRing(number)));
+        }
+        self.finish()
+    }
+
+    fn ring(&mut self, atom: usize, bond: Option<Bond>) -> Result<(), SmilesError> {
+        let position = self.pos;
+        let number = if self.peek() == Some('%') {
+            self.pos += 1;
+            let digits = self.chars.get(self.pos..self.pos + 2).filter(|d| d.iter().all(char::is_ascii_digit));
+            let Some(digits) = digits else { return Err(self.unexpected()) };
+            self.pos += 2;
+            digits.iter().collect::<String>().parse().unwrap_or(0)
+        } else {
+            let digit = self.chars[self.pos].to_digit(10).unwrap_or(0);
+            self.pos += 1;
+            digit
+        };
+
+        match self.rings.remove(&number) {
+            None => {
+                self.rings.insert(number, (atom, bond, position));
+            }
+            Some((other, other_bond, _)) => {
+                if other == atom {
+                    return Err(self.error(position, SmilesErrorKind::RingToSelf(number)));
+                }
+                let b = match (other_bond, bond) {
+                    (Some(a), Some(b)) if a != b => return Err(self.error(position, SmilesErrorKind::RingBondMismatch(number))),
+                    (Some(b), _) | (None, Some(b)) => b,
+                    (None, None) if self.atoms[other].aromatic && self.atoms[atom].aromatic => Bond::Aromatic,
+                    (None, None) => Bond::Single,
+                };
+                self.bonds.push((other, atom, b));
+            }
+        }
+        Ok(())
+    }
+
+    fn organic_atom(&mut self) -> Result<usize, SmilesError> {
+        let c = self.chars[self.pos];
+        let next = self.chars.get(self.pos + 1).copied();
+        let (symbol, aromatic) = match (c, next) {
+            ('C', Some('l')) => ("Cl".to_string(), false),
+            ('B', Some('r')) => ("Br".to_string(), false),
+            ('B' | 'C' | 'N' | 'O' | 'P' | 'S' | 'F' | 'I', _) => (c.to_string(), false),
+            ('b' | 'c' | 'n' | 'o' | 'p' | 's', _) => (c.to_ascii_uppercase().to_string(), true),
+            _ => return Err(self.unexpected()),
+        };
+        let position = self.pos;
+        self.pos += symbol.len();
+        self.atoms.push(SmilesAtom { symbol, aromatic, hydrogens: None, charge: 0, position });
+        Ok(self.atoms.len() - 1)
+    }
+
+    fn bracket_atom(&mut self) -> Result<usize, SmilesError> {
+        let open_pos = self.pos;
+        self.pos += 1;
+        // isotope
+        self.number()?;
+
+        let (symbol, aromatic) = match self.peek() {
+            Some(c) if c.is_ascii_uppercase() => {
+                let mut symbol = c.to_string();
+                self.pos += 1;
+                if let Some(l) = self.peek().filter(char::is_ascii_lowercase) {
+                    symbol.push(l);
+                    self.pos += 1;
+                }
+                (symbol, false)
+            }
+            Some(c) if c.is_ascii_lowercase() => {
+                let two: String = self.chars[self.pos..].iter().take(2).collect();
+                let symbol = if two == "se" || two == "as" { two } else { c.to_string() };
+                self.pos += symbol.len();
+                (symbol[..1].to_ascii_uppercase() + &symbol[1..], true)
+            }
+            None => return Err(self.error(open_pos, SmilesErrorKind::UnclosedAtom)),
+            Some(_) => return Err(self.unexpected()),
+        };
+
+        // chirality, e.g. `@`, `@@` or `@TH1`
+        while self.peek() == Some('@') {
+            self.pos += 1;
+        }
+        let class: String = self.chars[self.pos..].iter().take(2).collect();
+        if ["TH", "AL", "SP", "TB", "OH"].contains(&class.as_str()) {
+            self.pos += 2;
+            self.number()?;
+        }
+
+        let mut hydrogens = 0;
+        let mut atom_charge = 0;
+        if self.peek() == Some('H') {
+            self.pos += 1;
+            hydrogens = self.number()?.unwrap_or(1);
+        }
+
+        if let Some(sign) = self.peek().and_then(|c| match c { '+' => Some(1), '-' => Some(-1), _ => None }) {
+            let sign_char = self.chars[self.pos];
+            let magnitude_pos = self.pos;
+            self.pos += 1;
+            let magnitude = match self.number()? {
+                Some(magnitude) => magnitude,
+                None => {
+                    let mut magnitude = 1;
+                    while self.peek() == Some(sign_char) {
+                        magnitude += 1;
+                        self.pos += 1;
+                    }
+                    magnitude
+                }
+            };
+            let charge = i32::try_from(magnitude).map(|m| sign * m)
+                .map_err(|_| self.error(magnitude_pos, SmilesErrorKind::CountOverflow))?;
+            self.charge = Some(self.charge.unwrap_or(0) + charge);
+            atom_charge = charge;
+        }
+
+        if self.peek() == Some(':') {
+            self.pos += 1;
+            if self.number()?.is_none() {
+                return Err(self.unexpected());
+            }
+        }
+
+        match self.peek() {
+            Some(']') => self.pos += 1,
+            None => return Err(self.error(open_pos, SmilesErrorKind::UnclosedAtom)),
+            Some(_) => return Err(self.unexpected()),
+        }
+        self.atoms.push(SmilesAtom { symbol, aromatic, hydrogens: Some(hydrogens), charge: atom_charge, position: open_pos });
+        Ok(self.atoms.len() - 1)
+    }
+
+    fn number(&mut self) -> Result<Option<u32>, SmilesError> {
+        let start = self.pos;
+        let mut number: Option<u32> = None;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(10)) {
+            number = Some(number.unwrap_or(0).checked_mul(10)
+                .and_then(|x| x.checked_add(digit))
+                .ok_or_else(|| self.error(start, SmilesErrorKind::CountOverflow))?);
+            self.pos += 1;
+        }
+        Ok(number)
+    }
+
+    fn implicit_hydrogens(&self, atom: usize) -> u32 {
+        let bonds = self.bonds.iter()
+            .filter(|&&(a, b, _)| a == atom || b == atom)
+            .map(|&(_, _, bond)| bond.order())
+            .sum::<u32>();
+        let valences = usual_valences(&self.atoms[atom].symbol);
+        if self.atoms[atom].aromatic {
+            // one more bond shared around the ring
+            valences[0].saturating_sub(bonds + 1)
+        } else {
+            valences.iter().find(|&&v| v >= bonds).map_or(0, |&v| v - bonds)
+        }
+    }
+
+    // aromatic atoms that need a double bond in the ring: those left short of their valence by their bonds and
+    // hydrogens, e.g. every c of benzene but not the [nH] of pyrrole or the o of furan
+    fn needs_pi_bond(&self, atom: usize, hydrogens: u32) -> bool {
+        let smiles_atom = &self.atoms[atom];
+        let bonds = self.bonds.iter()
+            .filter(|&&(a, b, _)| a == atom || b == atom)
+            .map(|&(_, _, bond)| bond.order())
+            .sum::<u32>();
+        // a charged atom bonds like the neutral one with as many valence electrons, e.g. [n+] like C and [o+] like N
+        let usual = usual_valences(&smiles_atom.symbol)[0] as i32;
+        let valence_electrons = match smiles_atom.symbol.as_str() {
+            "B" => 3,
+            "C" => 4,
+            _ => 8 - usual,
+        } - smiles_atom.charge;
+        let valence = if smiles_atom.charge == 0 { usual } else if valence_electrons <= 4 { valence_electrons } else { 8 - valence_electrons };
+        smiles_atom.aromatic && valence > (bonds + hydrogens) as i32
+    }
+
+    // pi bonds of every bond in one Kekulé structure: each aromatic atom that needs a double bond gets one to an
+    // aromatic neighbor, trying the atoms with the fewest neighbors left first and backing out of dead ends
+    fn kekulize(&self, hydrogens: &[u32]) -> Result<Vec<u32>, SmilesError> {
+        let needs = (0..self.atoms.len()).map(|i| self.needs_pi_bond(i, hydrogens[i])).collect::<Vec<_>>();
+        let mut pi = self.bonds.iter().map(|&(_, _, bond)| *bond.pi_bonds().start()).collect::<Vec<_>>();
+        let candidates = (0..self.bonds.len())
+            .filter(|&e| self.bonds[e].2 == Bond::Aromatic && needs[self.bonds[e].0] && needs[self.bonds[e].1])
+            .collect::<Vec<_>>();
+        let mut matched = needs.iter().map(|&need| !need).collect::<Vec<_>>();
+        if match_atoms(&self.bonds, &candidates, &mut matched, &mut pi) {
+            Ok(pi)
+        } else {
+            let first = self.atoms.iter().filter(|atom| atom.aromatic).map(|atom| atom.position).min().unwrap_or(0);
+            Err(self.error(first, SmilesErrorKind::NotKekulizable))
+        }
+    }
+
+    fn finish(&mut self) -> Result<ParsedSmiles, SmilesError> {
+        let heavy_count = self.atoms.len();
+        let mut bonds: Vec<(usize, usize)> = self.bonds.iter().map(|&(a, b, _)| (a, b)).collect();
+        let hydrogen_counts = (0..heavy_count)
+            .map(|atom| self.atoms[atom].hydrogens.unwrap_or_else(|| self.implicit_hydrogens(atom)))
+            .collect::<Vec<_>>();
+        let mut kekule = self.kekulize(&hydrogen_counts)?;
+        // aromatic bonds next to an atom without a double bond of its own, like the N-C bonds of pyrrole, stay single
+        let mut pi_bonds: Vec<RangeInclusive<u32>> = self.bonds.iter().map(|&(a, b, bond)| match bond {
+            Bond::Aromatic if !self.needs_pi_bond(a, hydrogen_counts[a]) || !self.needs_pi_bond(b, hydrogen_counts[b]) => 0..=0,
+            _ => bond.pi_bonds(),
+        }).collect();
+
+        let mut symbols: Vec<String> = self.atoms.iter().map(|a| a.symbol.clone()).collect();
+        for (atom, &hydrogens) in hydrogen_counts.iter().enumerate() {
+            for _ in 0..hydrogens {
+                symbols.push("H".to_string());
+                bonds.push((atom, symbols.len() - 1));
+                pi_bonds.push(0..=0);
+                kekule.push(0);
+            }
+        }
+
+        let terms = symbols.iter().map(|symbol| FormulaTerm { symbol: symbol.clone(), count: 1 }).collect();
+        let parsed = ParsedFormula { terms, formula: hill_formula(&symbols), charge: self.charge, fragments: self.fragments };
+        Ok(ParsedSmiles { parsed, bonds, pi_bonds, kekule })
+    }
+}
+
+// gives every unmatched atom a pi bond along one of the candidate bonds to another unmatched atom; the atom with
+// the fewest of them goes first, so forced choices are made before free ones
+fn match_atoms(bonds: &[(usize, usize, Bond)], candidates: &[usize], matched: &mut [bool], pi: &mut [u32]) -> bool {
+    let open = |atom: usize, matched: &[bool]| candidates.iter().copied()
+        .filter(|&e| (bonds[e].0 == atom || bonds[e].1 == atom) && !matched[bonds[e].0] && !matched[bonds[e].1])
+        .collect::<Vec<_>>();
+    let Some(atom) = (0..matched.len()).filter(|&i| !matched[i]).min_by_key(|&i| open(i, matched).len()) else {
+        return true;
+    };
+    for e in open(atom, matched) {
+        let (a, b, _) = bonds[e];
+        matched[a] = true;
+        matched[b] = true;
+        pi[e] = 1;
+        if match_atoms(bonds, candidates, matched, pi) {
+            return true;
+        }
+        matched[a] = false;
+        matched[b] = false;
+        pi[e] = 0;
+    }
+    false
+}
+
+// molecular formula in Hill order: C, then H, then the rest alphabetically (all alphabetically without carbon)
+fn hill_formula(symbols: &[String]) -> String {
+    let mut counts: Vec<(&str, usize)> = vec![];
+    for symbol in symbols {
+        match counts.iter_mut().find(|(s, _)| s == symbol) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((symbol, 1)),
+        }
+    }
+    let has_carbon = counts.iter().any(|&(s, _)| s == "C");
+    counts.sort_by_key(|&(s, _)| match s {
+        "C" if has_carbon => (0, s),
+        "H" if has_carbon => (1, s),
+        _ => (2, s),
+    });
+    counts.iter()
+        .map(|&(s, count)| if count == 1 { s.to_string() } else { format!("{}{}", s, count) })
+        .collect()
+}
+
+// parses a SMILES string into its atoms, with hydrogens made explicit, the bonds between them and the total charge
+// of its bracket atoms if any is written
+pub fn parse_smiles(input: &str) -> Result<ParsedSmiles, SmilesError> {
+    Parser::new(input).smiles()
+}
+
+// atoms that can be written without brackets when their hydrogens follow from their usual valence
+const ORGANIC_SUBSET: [&str; 10] = ["B", "C", "N", "O", "P", "S", "F", "Cl", "Br", "I"];
+
+// ranks every value by its place among the distinct values, equal values sharing a rank
+fn dense_ranks<K: Ord>(keys: &[K]) -> Vec<usize> {
+    let distinct = keys.iter().sorted().dedup().collect::<Vec<_>>();
+    keys.iter().map(|k| distinct.binary_search(&k).unwrap_or(0)).collect()
+}
+
+// ranks that only depend on the graph, not on the order the atoms are stored in: atoms are ranked by their own
+// invariants, then repeatedly by their neighbors' ranks; atoms that stay tied are symmetric, so splitting them
+// off one at a time gives the same string whichever one is picked
+fn canonical_ranks<K: Ord>(invariants: &[K], neighbors: &[Vec<(usize, u32)>]) -> Vec<usize> {
+    let count_distinct = |ranks: &[usize]| ranks.iter().unique().count();
+    let mut ranks = dense_ranks(invariants);
+    loop {
+        loop {
+            let keys = (0..ranks.len())
+                .map(|i| (ranks[i], neighbors[i].iter().map(|&(j, order)| (ranks[j], order)).sorted().collect::<Vec<_>>()))
+                .collect::<Vec<_>>();
+            let refined = dense_ranks(&keys);
+            let stable = count_distinct(&refined) == count_distinct(&ranks);
+            ranks = refined;
+            if stable {
+                break;
+            }
+        }
+
+        let tied = (0..ranks.len())
+            .filter(|&i| ranks.iter().filter(|&&r| r == ranks[i]).count() > 1)
+            .min_by_key(|&i| (ranks[i], i));
+        let Some(tied) = tied else { return ranks };
+        let split = ranks.iter().enumerate()
+            .map(|(i, &r)| if r == ranks[tied] && i != tied { 2 * r + 1 } else { 2 * r })
+            .collect::<Vec<_>>();
+        ranks = dense_ranks(&split);
+    }
+}
+
+fn bond_symbol(order: u32) -> &'static str {
+    match order {
+        2 => "=",
+        3 => "#",
+        4 => "$",
+        _ => "",
+    }
+}
+
+fn ring_label(digit: usize) -> String {
+    if digit < 10 { digit.to_string() } else { format!("%{}", digit) }
+}
+
+// depth first walk over the atoms that are written, in rank order
+struct Writer<'a> {
+    tokens: Vec<String>,
+    neighbors: &'a [Vec<(usize, u32)>],
+    ranks: Vec<usize>,
+    visit_order: Vec<Option<usize>>,
+    children: Vec<Vec<(usize, u32)>>,
+    // bonds closing a ring, from the atom visited first to the one visited last
+    ring_bonds: Vec<(usize, usize, u32)>,
+    ring_digits: Vec<Option<usize>>,
+    digits_in_use: Vec<bool>,
+}
+
+impl Writer<'_> {
+    fn visit(&mut self, atom: usize, parent: Option<usize>, counter: &mut usize) {
+        self.visit_order[atom] = Some(*counter);
+        *counter += 1;
+        let neighbors = self.neighbors[atom].iter().copied().sorted_by_key(|&(j, _)| self.ranks[j]).collect::<Vec<_>>();
+        for (next, order) in neighbors {
+            if Some(next) == parent {
+                continue;
+            }
+            match self.visit_order[next] {
+                None => {
+                    self.children[atom].push((next, order));
+                    self.visit(next, Some(atom), counter);
+                }
+                Some(earlier) if earlier < self.visit_order[atom].unwrap_or(0) => self.ring_bonds.push((next, atom, order)),
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn write(&mut self, atom: usize, out: &mut String) {
+        out.push_str(&self.tokens[atom]);
+
+        // closing ring bonds first so their digits can be used again right away
+        let closing = (0..self.ring_bonds.len()).filter(|&r| self.ring_bonds[r].1 == atom)
+            .sorted_by_key(|&r| self.visit_order[self.ring_bonds[r].0]).collect::<Vec<_>>();
+        for r in closing {
+            let digit = self.ring_digits[r].take().unwrap_or(0);
+            self.digits_in_use[digit] = false;
+            out.push_str(&ring_label(digit));
+        }
+        let opening = (0..self.ring_bonds.len()).filter(|&r| self.ring_bonds[r].0 == atom)
+            .sorted_by_key(|&r| self.visit_order[self.ring_bonds[r].1]).collect::<Vec<_>>();
+        for r in opening {
+            let digit = (1..).find(|&d| !self.digits_in_use.get(d).copied().unwrap_or(false)).unwrap_or(1);
+            if digit >= self.digits_in_use.len() {
+                self.digits_in_use.resize(digit + 1, false);
+            }
+            self.digits_in_use[digit] = true;
+            self.ring_digits[r] = Some(digit);
+            out.push_str(bond_symbol(self.ring_bonds[r].2));
+            out.push_str(&ring_label(digit));
+        }
+
+        // every child but the last is a branch
+        let children = self.children[atom].clone();
+        for (k, &(child, order)) in children.iter().enumerate() {
+            let branch = k + 1 < children.len();
+            if branch {
+                out.push('(');
+            }
+            out.push_str(bond_symbol(order));
+            self.write(child, out);
+            if branch {
+                out.push(')');
+            }
+        }
+    }
+}
+
+fn atom_token(symbol: &str, charge: i32, hydrogens: u32, bond_orders: u32, radical: bool) -> String {
+    if charge == 0 && !radical && ORGANIC_SUBSET.contains(&symbol) {
+        let implicit = usual_valences(symbol).iter().find(|&&v| v >= bond_orders).map_or(0, |&v| v - bond_orders);
+        if implicit == hydrogens {
+            return symbol.to_string();
+        }
+    }
+    let hydrogens = match hydrogens {
+        0 => String::new(),
+        1 => "H".to_string(),
+        n => format!("H{}", n),
+    };
+    let charge = match charge {
+        0 => String::new(),
+        1 => "+".to_string(),
+        -1 => "-".to_string(),
+        c => format!("{:+}", c),
+    };
+    format!("[{}{}{}]", symbol, hydrogens, charge)
+}
+
+// writes canonical SMILES for atoms given by symbol and formal charge, bonded with the given bond orders;
+// hydrogens singly bonded to another atom are folded into it, bonds are written in their kekulized form and atoms
+// with an unpaired electron as bracket atoms, which SMILES reads as short of their usual hydrogens
+pub fn write_smiles(symbols: &[String], charges: &[i32], radicals: &[bool], bonds: &[(usize, usize, u32)]) -> String {
+    let count = symbols.len();
+    let mut all_neighbors: Vec<Vec<(usize, u32)>> = vec![vec![]; count];
+    for &(a, b, order) in bonds {
+        all_neighbors[a].push((b, order));
+        all_neighbors[b].push((a, order));
+    }
+
+    let folded = (0..count).map(|i| symbols[i] == "H" && charges[i] == 0
+        && matches!(all_neighbors[i][..], [(j, 1)] if symbols[j] != "H")).collect::<Vec<_>>();
+    let hydrogens = (0..count)
+        .map(|i| all_neighbors[i].iter().filter(|&&(j, _)| folded[j]).count() as u32)
+        .collect::<Vec<_>>();
+    let neighbors = (0..count)
+        .map(|i| all_neighbors[i].iter().copied().filter(|&(j, _)| !folded[j]).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let invariants = (0..count).map(|i| (
+        folded[i],
+        neighbors[i].len(),
+        symbols[i].clone(),
+        hydrogens[i],
+        charges[i],
+        radicals[i],
+        neighbors[i].iter().map(|&(_, order)| order).sum::<u32>(),
+    )).collect::<Vec<_>>();
+    let tokens = (0..count)
+        .map(|i| atom_token(&symbols[i], charges[i], hydrogens[i], neighbors[i].iter().map(|&(_, order)| order).sum(), radicals[i]))
+        .collect();
+
+    let mut writer = Writer {
+        tokens,
+        neighbors: &neighbors,
+        ranks: canonical_ranks(&invariants, &neighbors),
+        visit_order: vec![None; count],
+        children: vec![vec![]; count],
+        ring_bonds: vec![],
+        ring_digits: vec![],
+        digits_in_use: vec![true],
+    };
+
+    // one dot separated part per connected piece, starting from its lowest ranked atom
+    let mut parts = vec![];
+    let mut counter = 0;
+    for start in (0..count).filter(|&i| !folded[i]).sorted_by_key(|&i| writer.ranks[i]) {
+        if writer.visit_order[start].is_some() {
+            continue;
+        }
+        writer.visit(start, None, &mut counter);
+        writer.ring_digits.resize(writer.ring_bonds.len(), None);
+        let mut part = String::new();
+        writer.write(start, &mut part);
+        parts.push(part);
+    }
+    parts.join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // symbols of the heavy atoms with their hydrogens, e.g. `N3` for NH3, the formula and the charge
+    fn atoms(input: &str) -> (Vec<String>, String, Option<i32>) {
+        let smiles = parse_smiles(input).unwrap();
+        let symbols = smiles.parsed.terms.iter().map(|t| t.symbol.as_str()).collect::<Vec<_>>();
+        let heavy = symbols.iter().enumerate().filter(|&(_, &s)| s != "H").map(|(i, &s)| {
+            let hydrogens = smiles.bonds.iter().filter(|&&(a, b)| (a == i && symbols[b] == "H") || (b == i && symbols[a] == "H")).count();
+            format!("{}{}", s, hydrogens)
+        }).collect();
+        (heavy, smiles.parsed.formula, smiles.parsed.charge)
+    }
+
+    fn error_kind(input: &str) -> (SmilesErrorKind, usize) {
+        let error = parse_smiles(input).unwrap_err();
+        (error.kind, error.position)
+    }
+
+    #[test]
+    fn implicit_hydrogens_follow_the_usual_valences() {
+        assert_eq!(atoms("N").0, ["N3"]);
+        assert_eq!(atoms("P").0, ["P3"]);
+        assert_eq!(atoms("S").0, ["S2"]);
+        assert_eq!(atoms("N(=O)=O").0, ["N1", "O0", "O0"]);
+        assert_eq!(atoms("CS(=O)(=O)C").0, ["C3", "S0", "O0", "O0", "C3"]);
+        assert_eq!(atoms("CS(=O)C").0, ["C3", "S0", "O0", "C3"]);
+        assert_eq!(atoms("CCl").0, ["C3", "Cl0"]);
+        assert_eq!(atoms("CCO").1, "C2H6O");
+    }
+
+    #[test]
+    fn bracket_atoms_keep_their_hydrogens_and_charges() {
+        assert_eq!(atoms("[NH4+]"), (vec!["N4".to_string()], "H4N".to_string(), Some(1)));
+        assert_eq!(atoms("[CH2]").0, ["C2"]);
+        assert_eq!(atoms("[O-]").2, Some(-1));
+        assert_eq!(atoms("[O-2]").2, Some(-2));
+        assert_eq!(atoms("[Fe++]").2, Some(2));
+        assert_eq!(atoms("CC(=O)[O-]").2, Some(-1));
+        assert_eq!(atoms("[NH4+].[Cl-]").2, Some(0));
+        assert_eq!(atoms("[13CH4]").0, ["C4"]);
+        assert_eq!(atoms("N[C@@H](C)C(=O)O").0, ["N2", "C1", "C3", "C0", "O0", "O1"]);
+    }
+
+    #[test]
+    fn ring_closures() {
+        let ring = parse_smiles("C1CC1").unwrap();
+        assert_eq!(&ring.bonds[..3], [(0, 1), (1, 2), (0, 2)]);
+        assert_eq!(parse_smiles("C%10CC%10").unwrap().bonds, ring.bonds);
+        // a ring number can be used again once it is closed
+        assert_eq!(atoms("C1CC1C1CC1").0, ["C2", "C2", "C1", "C1", "C2", "C2"]);
+        // the bond can be written at either end
+        assert_eq!(parse_smiles("C=1CC1").unwrap().pi_bonds[2], 1..=1);
+        assert_eq!(parse_smiles("C1CC=1").unwrap().pi_bonds[2], 1..=1);
+    }
+
+    // aromatic bonds may have a pi bond or not, which the solver decides
+    #[test]
+    fn aromatic_bonds_leave_pi_bonds_open() {
+        let benzene = parse_smiles("c1ccccc1").unwrap();
+        assert_eq!(&benzene.pi_bonds[..6], vec![0..=1; 6]);
+        assert!(benzene.pi_bonds[6..].iter().all(|pi| *pi == (0..=0)));
+        assert_eq!(atoms("c1ccccc1").0, ["C1"; 6]);
+        let chlorobenzene = parse_smiles("Clc1ccccc1").unwrap();
+        assert_eq!(chlorobenzene.pi_bonds[0], 0..=0);
+        assert_eq!(atoms("c1cc[nH]c1").0, ["C1", "C1", "C1", "N1", "C1"]);
+        assert_eq!(atoms("c1ccncc1").0, ["C1", "C1", "C1", "N0", "C1", "C1"]);
+    }
+
+    // one Kekulé structure gives every aromatic atom that needs it exactly one double bond
+    #[test]
+    fn aromatic_rings_are_kekulized() {
+        for input in ["c1ccccc1", "c1ccncc1", "c1cc2ccc3ccc4ccc5ccc6ccc1c1c2c3c4c5c61"] {
+            let smiles = parse_smiles(input).unwrap();
+            let symbols = smiles.parsed.terms.iter().map(|t| t.symbol.as_str()).collect::<Vec<_>>();
+            for atom in (0..symbols.len()).filter(|&i| symbols[i] != "H") {
+                let double = smiles.bonds.iter().zip(&smiles.kekule).filter(|&(&(a, b), _)| a == atom || b == atom).map(|(_, &pi)| pi).sum::<u32>();
+                assert_eq!(double, 1, "{} atom {}", input, atom);
+            }
+        }
+        // the NH of pyrrole keeps its bonds single
+        let pyrrole = parse_smiles("c1cc[nH]c1").unwrap();
+        assert_eq!(pyrrole.kekule.iter().sum::<u32>(), 2);
+        assert_eq!(pyrrole.pi_bonds[2], 0..=0);
+        assert_eq!(pyrrole.pi_bonds[3], 0..=0);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error_kind(""), (SmilesErrorKind::Empty, 0));
+        assert_eq!(error_kind("Cc1cccc1"), (SmilesErrorKind::NotKekulizable, 1));
+        assert_eq!(error_kind("C[C]]"), (SmilesErrorKind::UnexpectedChar(']'), 4));
+        assert_eq!(error_kind("C="), (SmilesErrorKind::UnexpectedEnd, 2));
+        assert_eq!(error_kind("C[C"), (SmilesErrorKind::UnclosedAtom, 1));
+        assert_eq!(error_kind("CC(C"), (SmilesErrorKind::UnclosedBranch, 2));
+        assert_eq!(error_kind("CC)"), (SmilesErrorKind::UnmatchedClose, 2));
+        assert_eq!(error_kind("CC1CC"), (SmilesErrorKind::UnclosedRing(1), 2));
+        assert_eq!(error_kind("C=1CC#1"), (SmilesErrorKind::RingBondMismatch(1), 6));
+        assert_eq!(error_kind("CC11"), (SmilesErrorKind::RingToSelf(1), 3));
+        assert_eq!(error_kind("[99999999999C]"), (SmilesErrorKind::CountOverflow, 1));
+        assert_eq!(error_kind("C%1"), (SmilesErrorKind::UnexpectedChar('1'), 2));
+    }
+
+    #[test]
+    fn errors_point_at_the_position() {
+        assert_eq!(parse_smiles("CC)").unwrap_err().to_string(), "')' at position 2 has no matching '('\n  CC)\n    ^");
+        assert_eq!(parse_smiles("").unwrap_err().to_string(), "empty SMILES");
+    }
+}
@@ -1,38 +1,126 @@
-use std::cell::RefCell;
-use std::env;
-use std::fs::File;
-use std::iter::zip;
-use std::rc::Rc;
-use csv::{Reader};
+use itertools::Itertools;
+use schemars::{schema_for, JsonSchema};
 use serde::{Serialize, Deserialize};
-use std::io::BufWriter;
-
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
-pub struct Element {
-    name: String,
-    electroneg: u32,
-    config: Vec<String>,
-    id: u32
-}
+use std::ops::RangeInclusive;
+use crate::diagram::{render_ascii, DiagramAtom};
+use crate::element::{Element, ElementTable, GroupBlock};
+use crate::error::LewisError;
+use crate::formula::{parse_condensed, parse_formula};
+use crate::geometry::{axe_notation, Geometry};
+use crate::coordinates::{vsepr_coordinates, xyz_block, VseprAtom};
+use crate::layout::layout_2d;
+use crate::molfile::{molfile_block, MolAtom, MolfileVersion};
+use crate::smiles::{parse_smiles, write_smiles};
+use crate::svg::render_svg;
+use crate::validate::Violation;
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct Atom {
     name: String,
     valence: u32,
     lone: u32,
+    // nonbonding electrons that are not paired up, 1 on the atom carrying the odd electron of a radical
+    unpaired: u32,
     hybridization: Hybridization,
     spd_orbitals: Vec<u8>,
     p_orbitals: Vec<u8>,
-    id: u32
+    id: u32,
+    atomic_number: u32,
+    formal_charge: i32,
+    // covalent radius in picometres, if the element table has one
+    covalent_radius: Option<u32>,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Atom {
+    pub fn symbol(&self) -> &str {
+        self.name.trim()
+    }
+
+    // counts atoms of the same element within the molecule, as in `--bonds` and the json output
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn valence(&self) -> u32 {
+        self.valence
+    }
+
+    // nonbonding electrons
+    pub fn lone_electrons(&self) -> u32 {
+        self.lone
+    }
+
+    pub fn lone_pairs(&self) -> u32 {
+        self.lone.saturating_sub(self.unpaired) / 2
+    }
+
+    pub fn unpaired_electrons(&self) -> u32 {
+        self.unpaired
+    }
+
+    pub fn hybridization(&self) -> Hybridization {
+        self.hybridization
+    }
+
+    // electrons in each hybrid orbital, one orbital per sigma bond, lone pair or unpaired electron
+    pub fn spd_orbitals(&self) -> &[u8] {
+        &self.spd_orbitals
+    }
+
+    // electrons in each unhybridized p orbital, one orbital per pi bond
+    pub fn p_orbitals(&self) -> &[u8] {
+        &self.p_orbitals
+    }
+
+    pub fn atomic_number(&self) -> u32 {
+        self.atomic_number
+    }
+
+    // valence - nonbonding electrons - bonding electrons / 2, set once the model is built
+    pub fn formal_charge(&self) -> i32 {
+        self.formal_charge
+    }
+
+    // in picometres, if the element table has one
+    pub fn covalent_radius(&self) -> Option<u32> {
+        self.covalent_radius
+    }
+}
+
+// index of an atom in Model::atoms
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct AtomId(pub usize);
+
+// a bond between two atoms of a model with its sigma and pi bonds
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct Bond {
+    pub atoms: (AtomId, AtomId),
+    pub sigma: u32,
+    pub pi: u32,
+}
+
+impl Bond {
+    pub fn order(&self) -> u32 {
+        self.sigma + self.pi
+    }
+
+    // the atom at the other end from `id`
+    pub fn other(&self, id: AtomId) -> AtomId {
+        if self.atoms.0 == id { self.atoms.1 } else { self.atoms.0 }
+    }
+}
+
+// variant names double as the bond_type strings in the json output
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum BondType {
     SIGMA,
     PI,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+// variant names double as the hybridization strings in the json output
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum Hybridization {
     S,
     SP,
@@ -46,30 +134,19 @@ pub enum Hybridization {
 }
 
 impl Hybridization {
-    fn next(self) -> Option<Hybridization> {
-        match self {
-            Hybridization::S => Some(Hybridization::SP),
-            Hybridization::SP => Some(Hybridization::SP2),
-            Hybridization::SP2 => Some(Hybridization::SP3),
-            Hybridization::SP3 => Some(Hybridization::SP3D),
-            Hybridization::SP3D => Some(Hybridization::SP3D2),
-            Hybridization::SP3D2 => Some(Hybridization::SP3D3),
-            Hybridization::SP3D3 => Some(Hybridization::SP3D4),
-            Hybridization::SP3D4 => Some(Hybridization::SP3D5),
-            Hybridization::SP3D5 => None
-        }
-    }
-    fn before(self) -> Option<Hybridization> {
-        match self {
-            Hybridization::S => None,
-            Hybridization::SP => Some(Hybridization::S),
-            Hybridization::SP2 => Some(Hybridization::SP),
-            Hybridization::SP3 => Some(Hybridization::SP2),
-            Hybridization::SP3D => Some(Hybridization::SP3),
-            Hybridization::SP3D2 => Some(Hybridization::SP3D),
-            Hybridization::SP3D3 => Some(Hybridization::SP3D2),
-            Hybridization::SP3D4 => Some(Hybridization::SP3D3),
-            Hybridization::SP3D5 => Some(Hybridization::SP3D4),
+    // hybrid orbitals needed for the given number of sigma bonds and lone pairs
+    fn from_steric_number(steric_number: usize) -> Result<Hybridization, LewisError> {
+        match steric_number {
+            0 | 1 => Ok(Hybridization::S),
+            2 => Ok(Hybridization::SP),
+            3 => Ok(Hybridization::SP2),
+            4 => Ok(Hybridization::SP3),
+            5 => Ok(Hybridization::SP3D),
+            6 => Ok(Hybridization::SP3D2),
+            7 => Ok(Hybridization::SP3D3),
+            8 => Ok(Hybridization::SP3D4),
+            9 => Ok(Hybridization::SP3D5),
+            _ => Err(LewisError::HybridizationOverflow),
         }
     }
 }
@@ -78,486 +155,1062 @@ impl Hybridization {
 pub struct ParsedMolecule {
     name: String,
     elements: Vec<Element>,
-    charge: i32
+    charge: i32,
+    // sigma bonds given by the user; without them build_model picks a skeleton itself
+    skeleton: Option<Skeleton>,
+    // pi bonds each bond of the given skeleton may have, when they are known (SMILES); empty otherwise
+    pi_bonds: Vec<RangeInclusive<u32>>,
+    // pi bonds of each bond in one Kekulé structure of aromatic SMILES, which the search starts from; empty otherwise
+    kekule: Vec<u32>,
+    // molecules or ions written apart, as in hydrates
+    fragments: usize,
+}
+
+impl ParsedMolecule {
+    // formula without its charge suffix followed by the charge, e.g. `SO4_-2`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // fixes the skeleton to a comma separated list of bonds between atoms named by symbol and id, the way the
+    // json output names them, e.g. `C0-C1,C1-O0` (atoms of an element are numbered from 0 in formula order);
+    // hydrogens that are not listed are attached where the solver would put them
+    pub fn with_bonds(mut self, bonds: &str) -> Result<Self, LewisError> {
+        let find = |label: &str| self.elements.iter().position(|e| format!("{}{}", e.name.trim(), e.id) == label)
+            .ok_or_else(|| LewisError::BadConnectivity(format!("no atom {} in {}", label, self.name)));
+
+        let mut skeleton: Skeleton = vec![];
+        for bond in bonds.split(',').map(str::trim).filter(|b| !b.is_empty()) {
+            let (a, b) = bond.split_once('-')
+                .ok_or_else(|| LewisError::BadConnectivity(format!("'{}' is not written as ATOM-ATOM", bond)))?;
+            let (a, b) = (find(a.trim())?, find(b.trim())?);
+            if a == b {
+                return Err(LewisError::BadConnectivity(format!("{} cannot bond to itself", bond)));
+            }
+            if skeleton.iter().any(|&(x, y)| (x, y) == (a, b) || (x, y) == (b, a)) {
+                return Err(LewisError::BadConnectivity(format!("{} is listed more than once", bond)));
+            }
+            skeleton.push((a, b));
+        }
+        self.skeleton = Some(skeleton);
+        self.pi_bonds = vec![];
+        self.kekule = vec![];
+        Ok(self)
+    }
 }
 
-pub type AtomRef = Rc<RefCell<Atom>>;
+// how the formula argument is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    // element counts, e.g. `C2H6O`; the skeleton is up to build_model
+    Formula,
+    // condensed formula, e.g. `CH3CH2OH`, which fixes the skeleton
+    Condensed,
+    // SMILES, e.g. `CCO`, which fixes the skeleton and the bond orders apart from aromatic bonds
+    Smiles,
+}
 
 #[derive(Debug, Clone)]
 pub struct Model {
     name: String,
-    atoms: Vec<AtomRef>,
-    bonds_with: Vec<Vec<(AtomRef, BondType)>>,
+    // charge of the whole molecule
+    charge: i32,
+    atoms: Vec<Atom>,
+    bonds: Vec<Bond>,
+    // indices into `bonds` of the bonds of each atom, in the order they were added
+    bonds_of: Vec<Vec<usize>>,
 }
 
-#[derive(Serialize, Deserialize)]
+// version of the json written by Model::write_to_json, raised whenever a field changes meaning or goes away; new
+// fields get a serde default instead so that older files of the same version still read (files from before it was
+// introduced have no schema_version field)
+pub const SCHEMA_VERSION: u32 = 1;
+
+// the json types below are the contract with the display; their doc comments become the descriptions in the
+// published schema (see json_schema)
+
+/// One shared pair between the atom and another one.
+#[derive(Serialize, Deserialize, JsonSchema)]
 struct EntryBond {
+    /// Symbol of the other atom, padded to two characters.
     name: String,
+    /// Index of the other atom among the atoms of its element.
     id: u32,
-    bond_type: String,
+    bond_type: BondType,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 struct EntryAtom {
+    /// Element symbol, padded to two characters, e.g. `"O "` or `"Cl"`.
     name: String,
+    /// Valence electrons of the element.
     valence: u32,
+    /// Nonbonding electrons.
     lone: u32,
+    /// Index among the atoms of the same element, e.g. 1 for the second oxygen.
     id: u32,
-    hybridization: String,
+    hybridization: Hybridization,
+    /// Every sigma bond of the atom, then every pi bond; each bond is listed on both of its atoms.
     bonds_with: Vec<EntryBond>,
+    /// Electrons in each unhybridized p orbital, one per pi bond.
     p_orbitals: Vec<u8>,
+    /// Electrons in each hybrid orbital, one per sigma bond, lone pair or unpaired electron.
     spd_orbitals: Vec<u8>,
+    formal_charge: i32,
+    /// Arrangement of all bonded atoms and lone pairs, null if it has no name.
+    electron_geometry: Option<Geometry>,
+    /// Arrangement of the bonded atoms, null if it has no name.
+    molecular_geometry: Option<Geometry>,
+    /// AXE notation, e.g. `AX2E2`.
+    axe: String,
+    /// Whether the atom has less than an octet (a duet for H and He), e.g. the boron of BF3.
+    #[serde(default)]
+    electron_deficient: bool,
+    /// Nonbonding electrons that are not paired up, 1 on the atom with the odd electron of a radical.
+    #[serde(default)]
+    unpaired: u32,
+}
+
+/// One bond of the model, written once for both of its atoms.
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct EntryBondRecord {
+    /// Indices of the two atoms in the atoms array.
+    atoms: [usize; 2],
+    /// Bond order averaged over the resonance structures, e.g. 1.5 for the bonds of ozone.
+    order: f64,
+    /// Sigma bonds in this structure.
+    sigma: u32,
+    /// Pi bonds in this structure.
+    pi: u32,
 }
 
-#[derive(Serialize, Deserialize)]
+/// A Lewis structure as written by lewis-structure-solver.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(title = "Model")]
 struct EntryModel {
+    /// Version of this format, see SCHEMA_VERSION.
+    schema_version: u32,
+    /// Formula and charge, e.g. `SO4_-2`.
     name: String,
     atoms: Vec<EntryAtom>,
+    bonds: Vec<EntryBondRecord>,
+    /// Whether the molecule has an odd number of electrons, one of them unpaired.
+    #[serde(default)]
+    radical: bool,
+}
+
+// JSON Schema of the json written by Model::write_to_json, as published in schema/model.schema.json
+pub fn json_schema() -> String {
+    let mut schema = serde_json::to_string_pretty(&schema_for!(EntryModel)).unwrap_or_default();
+    schema.push('\n');
+    schema
 }
 
 impl Model {
-    fn new(name: String, atoms: Vec<AtomRef>) -> Self {
+    fn new(name: String, charge: i32, atoms: Vec<Atom>) -> Self {
         Model {
-            name: name,
-            atoms: atoms.clone(),
-            bonds_with: vec![Vec::new(); atoms.len()],
-        }
-    }
-
-    fn add_bond(&mut self, atom_1: AtomRef, atom_2: AtomRef, bond: BondType) {
-        let mut is_bond_possible = false;
-
-        {
-            let mut deref_atom_1 = atom_1.borrow_mut();
-            let mut deref_atom_2 = atom_2.borrow_mut();
-
-            match bond {
-                BondType::SIGMA => {
-                    if deref_atom_1.spd_orbitals.contains(&(1u8)) && deref_atom_2.spd_orbitals.contains(&(1u8)) {
-                        is_bond_possible = true;
-                        if let Some(index) = deref_atom_1.spd_orbitals.iter().position(|&x| x == 1) {
-                            deref_atom_1.spd_orbitals[index] = 2;
-                            deref_atom_1.lone -= 1;
-                        }
-                        if let Some(index) = deref_atom_2.spd_orbitals.iter().position(|&x| x == 1) {
-                            deref_atom_2.spd_orbitals[index] = 2;
-                            deref_atom_2.lone -= 1;
-                        }
-                    }
-                }
-                BondType::PI => {
-                    if deref_atom_1.p_orbitals.contains(&(1u8)) && deref_atom_2.p_orbitals.contains(&(1u8)) {
-                        is_bond_possible = true;
-                        if let Some(index) = deref_atom_1.p_orbitals.iter().position(|&x| x == 1) {
-                            deref_atom_1.p_orbitals[index] = 2;
-                            deref_atom_1.lone -= 1;
-                        }
-                        if let Some(index) = deref_atom_2.p_orbitals.iter().position(|&x| x == 1) {
-                            deref_atom_2.p_orbitals[index] = 2;
-                            deref_atom_2.lone -= 1;
-                        }
-                    }
+            name,
+            charge,
+            bonds: vec![],
+            bonds_of: vec![Vec::new(); atoms.len()],
+            atoms,
+        }
+    }
+
+    fn add_bond(&mut self, a: AtomId, b: AtomId, pi: u32) {
+        self.bonds_of[a.0].push(self.bonds.len());
+        self.bonds_of[b.0].push(self.bonds.len());
+        self.bonds.push(Bond { atoms: (a, b), sigma: 1, pi });
+    }
+
+    // every shared pair (sigma or pi) of an atom counts as one electron of its own
+    fn assign_formal_charges(&mut self) {
+        for (atom, bonds_of_atom) in self.atoms.iter_mut().zip(self.bonds_of.iter()) {
+            let shared = bonds_of_atom.iter().map(|&k| self.bonds[k].order()).sum::<u32>();
+            atom.formal_charge = atom.valence as i32 - atom.lone as i32 - shared as i32;
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // the atoms in the order of the formula, hydrogens of SMILES input last; AtomId indexes into them
+    pub fn atoms(&self) -> &[Atom] {
+        &self.atoms
+    }
+
+    pub fn atom(&self, id: AtomId) -> &Atom {
+        &self.atoms[id.0]
+    }
+
+    pub fn bonds(&self) -> &[Bond] {
+        &self.bonds
+    }
+
+    // the atoms bonded to `id` with the bond to each
+    pub fn neighbors(&self, id: AtomId) -> impl Iterator<Item = (AtomId, &Bond)> + '_ {
+        self.bonds_of[id.0].iter().map(move |&k| (self.bonds[k].other(id), &self.bonds[k]))
+    }
+
+    pub fn bond_between(&self, a: AtomId, b: AtomId) -> Option<&Bond> {
+        self.neighbors(a).find(|&(other, _)| other == b).map(|(_, bond)| bond)
+    }
+
+    pub fn charge(&self) -> i32 {
+        self.charge
+    }
+
+    pub fn total_formal_charge(&self) -> i32 {
+        self.atoms.iter().map(Atom::formal_charge).sum()
+    }
+
+    // bonds as atom index pairs (lower index first) with their order, listed by their lower atom
+    fn bond_orders(&self) -> Vec<(usize, usize, u32)> {
+        let mut bonds: Vec<(usize, usize, u32)> = vec![];
+        for (i, bonds_of_atom) in self.bonds_of.iter().enumerate() {
+            for &k in bonds_of_atom {
+                let j = self.bonds[k].other(AtomId(i)).0;
+                if i < j {
+                    bonds.push((i, j, self.bonds[k].order()));
                 }
             }
         }
+        bonds
+    }
 
-        if is_bond_possible {
-            let mut index_of_atom_1 = 0;
-            let mut index_of_atom_2 = 0;
+    // canonical SMILES of the model, with bond orders from the sigma and pi entries and formal charges written out
+    pub fn to_smiles(&self) -> String {
+        let symbols = self.atoms.iter().map(|atom| atom.symbol().to_string()).collect::<Vec<_>>();
+        let charges = self.atoms.iter().map(Atom::formal_charge).collect::<Vec<_>>();
+        let radicals = self.atoms.iter().map(|atom| atom.unpaired > 0).collect::<Vec<_>>();
+        write_smiles(&symbols, &charges, &radicals, &self.bond_orders())
+    }
 
-            for i in 0..self.atoms.len() {
-                if self.atoms[i] == atom_1 {
-                    index_of_atom_1 = i;
-                }
-                if self.atoms[i] == atom_2 {
-                    index_of_atom_2 = i;
-                }
+    // molfile block of the model with flat coordinates
+    pub fn to_molfile(&self, version: MolfileVersion) -> String {
+        let bonds = self.bond_orders();
+        let positions = layout_2d(self.atoms.len(), &bonds);
+        let atoms = self.atoms.iter().zip(positions).map(|(atom, position)| {
+            MolAtom { symbol: atom.name.trim().to_string(), charge: atom.formal_charge(), radical: atom.unpaired > 0, position }
+        }).collect::<Vec<_>>();
+        molfile_block(&self.name, &atoms, &bonds, "2D", version)
+    }
+
+    pub fn write_molfile(&self, path: String, version: MolfileVersion) -> std::io::Result<()> {
+        std::fs::write(path, self.to_molfile(version))
+    }
+
+    // bonded atoms and lone pairs around the atom, the electron domains VSEPR counts; an unpaired electron takes a
+    // domain of its own like a lone pair
+    pub fn domains(&self, id: AtomId) -> (usize, usize) {
+        let atom = &self.atoms[id.0];
+        (self.bonds_of[id.0].len(), (atom.lone_pairs() + atom.unpaired) as usize)
+    }
+
+    // electrons in the valence shell of the atom, its nonbonding electrons and two for every shared pair
+    pub fn shell_electrons(&self, id: AtomId) -> u32 {
+        self.atoms[id.0].lone + 2 * self.neighbors(id).map(|(_, bond)| bond.order()).sum::<u32>()
+    }
+
+    // atoms left with less than an octet (a duet for H and He), such as the boron of BF3 or the beryllium of BeCl2:
+    // group 2 and 13 atoms keep a formal charge of zero rather than take a pi bond from a neighbor; the atom with
+    // the odd electron of a radical is listed by radicals instead
+    pub fn electron_deficient(&self) -> Vec<AtomId> {
+        (0..self.atoms.len()).map(AtomId)
+            .filter(|&id| self.atoms[id.0].unpaired == 0)
+            .filter(|&id| {
+                let shell = if self.atoms[id.0].atomic_number <= 2 { 2 } else { 8 };
+                self.shell_electrons(id) < shell
+            })
+            .collect()
+    }
+
+    // atoms with an unpaired electron, such as the nitrogen of NO or the chlorine of ClO2
+    pub fn radicals(&self) -> Vec<AtomId> {
+        (0..self.atoms.len()).map(AtomId).filter(|&id| self.atoms[id.0].unpaired > 0).collect()
+    }
+
+    // shape of the molecule around its central atom, the one bonded to the most others (the first of them on a tie)
+    pub fn geometry(&self) -> Option<Geometry> {
+        let central = (0..self.atoms.len()).rev().max_by_key(|&i| self.bonds_of[i].len())?;
+        let (bonded, lone_pairs) = self.domains(AtomId(central));
+        Geometry::molecular(bonded, lone_pairs)
+    }
+
+    fn diagram_atoms(&self) -> Vec<DiagramAtom> {
+        self.atoms.iter().enumerate().map(|(i, atom)| {
+            let (bonded, lone_pairs) = self.domains(AtomId(i));
+            DiagramAtom {
+                symbol: atom.name.clone(),
+                id: atom.id,
+                lone: atom.lone,
+                charge: atom.formal_charge(),
+                geometry: Geometry::molecular(bonded, lone_pairs),
             }
+        }).collect()
+    }
 
-            let mut bonds_of_atom_1 = self.bonds_with.remove(index_of_atom_1);
-            bonds_of_atom_1.push((atom_2, bond));
-            self.bonds_with.insert(index_of_atom_1, bonds_of_atom_1);
+    // Lewis diagram for the terminal with lone pairs, bonds and formal charges
+    pub fn render_ascii(&self) -> String {
+        render_ascii(&self.diagram_atoms(), &self.bond_orders())
+    }
 
-            let mut bonds_of_atom_2 = self.bonds_with.remove(index_of_atom_2);
-            bonds_of_atom_2.push((atom_1, bond));
-            self.bonds_with.insert(index_of_atom_2, bonds_of_atom_2);
-        }
+    // the same diagram as an svg image, optionally with wedges and dashes for bonds leaving the page
+    pub fn to_svg(&self, wedges: bool) -> String {
+        render_svg(&self.name, &self.diagram_atoms(), &self.bond_orders(), wedges)
+    }
+
+    pub fn write_svg(&self, path: String, wedges: bool) -> std::io::Result<()> {
+        std::fs::write(path, self.to_svg(wedges))
+    }
+
+    // 3D coordinates in angstroms from the VSEPR geometry around each atom, in the order of the atoms
+    pub fn coordinates(&self) -> Vec<[f64; 3]> {
+        let atoms = self.atoms.iter().map(|atom| {
+            VseprAtom {
+                radius: atom.covalent_radius.map(f64::from),
+                steric_number: atom.spd_orbitals.len(),
+                hydrogen: atom.name == "H ",
+            }
+        }).collect::<Vec<_>>();
+        vsepr_coordinates(&atoms, &self.bond_orders())
+    }
+
+    pub fn to_xyz(&self) -> String {
+        let symbols = self.atoms.iter().map(|atom| atom.symbol().to_string()).collect::<Vec<_>>();
+        xyz_block(&self.name, &symbols, &self.coordinates())
+    }
+
+    pub fn write_xyz(&self, path: String) -> std::io::Result<()> {
+        std::fs::write(path, self.to_xyz())
+    }
+
+    // every sigma bond of an atom, then every pi bond, each with the atom at its other end
+    fn bond_entries(&self, id: AtomId) -> Vec<(&Atom, BondType)> {
+        let sigma = self.neighbors(id).flat_map(|(other, bond)| vec![(self.atom(other), BondType::SIGMA); bond.sigma as usize]);
+        let pi = self.neighbors(id).flat_map(|(other, bond)| vec![(self.atom(other), BondType::PI); bond.pi as usize]);
+        sigma.chain(pi).collect()
     }
 
     pub fn print_model(&self) {
         for i in 0..self.atoms.len() {
-            println!("{:?} -> {:?}\n", self.atoms[i].borrow(), self.bonds_with[i].iter()
-                .map(|(a, b)| {(a.borrow().clone(), *b)})
-                .collect::<Vec<(Atom, BondType)>>());
+            println!("{:?} -> {:?}\n", self.atoms[i], self.bond_entries(AtomId(i)));
         }
     }
 
-    // write Model to json file
-    pub fn write_to_json(&self, path: String) -> std::io::Result<()> { 
+    // order of each bond averaged over the resonance structures, or its own order without any
+    pub fn resonance_bond_orders(&self, resonance: &[Model]) -> Vec<f64> {
+        self.bonds.iter().map(|bond| {
+            if resonance.is_empty() {
+                return bond.order() as f64;
+            }
+            let total = resonance.iter()
+                .filter_map(|model| model.bond_between(bond.atoms.0, bond.atoms.1))
+                .map(|other| other.order())
+                .sum::<u32>();
+            total as f64 / resonance.len() as f64
+        }).collect()
+    }
+
+    // write Model to json file, with the bond orders averaged over `resonance` unless it is empty
+    pub fn write_to_json(&self, path: String, resonance: &[Model]) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json(resonance))
+    }
+
+    // the model as json in the format described by json_schema, with bond orders averaged over `resonance`
+    pub fn to_json(&self, resonance: &[Model]) -> String {
         // convert current struct to Entry struct so that it can be serialized
         // each Entry represents one atom in the model
-        let entries = self.atoms.iter().enumerate().map(|(i, a)| {
-            let atom = a.borrow();
+        let deficient = self.electron_deficient();
+        let entries = self.atoms.iter().enumerate().map(|(i, atom)| {
+            let (bonded, lone_pairs) = self.domains(AtomId(i));
             EntryAtom {
                 name: atom.name.clone(),
                 valence: atom.valence,
                 lone: atom.lone,
                 id: atom.id,
-                hybridization: format!("{:?}", atom.hybridization),
-                bonds_with: self.bonds_with[i].iter()
-                    .map(|(a, b)| { EntryBond {name: a.borrow().name.clone(), id: a.borrow().id, bond_type: format!("{:?}", b)}})
+                hybridization: atom.hybridization,
+                bonds_with: self.bond_entries(AtomId(i)).into_iter()
+                    .map(|(a, b)| { EntryBond {name: a.name.clone(), id: a.id, bond_type: b}})
                     .collect::<Vec<_>>(),
                 p_orbitals: atom.p_orbitals.clone(),
                 spd_orbitals: atom.spd_orbitals.clone(),
+                formal_charge: atom.formal_charge,
+                electron_geometry: Geometry::electron_domain(bonded + lone_pairs),
+                molecular_geometry: Geometry::molecular(bonded, lone_pairs),
+                axe: axe_notation(bonded, lone_pairs),
+                electron_deficient: deficient.contains(&AtomId(i)),
+                unpaired: atom.unpaired,
             }
         }).collect::<Vec<_>>();
+        let bonds = self.bonds.iter().zip(self.resonance_bond_orders(resonance)).map(|(bond, order)| EntryBondRecord {
+            atoms: [bond.atoms.0.0, bond.atoms.1.0],
+            order,
+            sigma: bond.sigma,
+            pi: bond.pi,
+        }).collect::<Vec<_>>();
         let entry_model: EntryModel = EntryModel {
+            schema_version: SCHEMA_VERSION,
             name: self.name.clone(),
             atoms: entries,
+            bonds,
+            radical: !self.radicals().is_empty(),
         };
 
-        let file = File::create(path)?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, &entry_model)?;
-        Ok(())
+        serde_json::to_string_pretty(&entry_model).unwrap_or_default()
     }
-}
 
-pub fn read_element_csv(file_path: &str, element_names: Vec<String>) -> Vec<Element> {
-    let mut elements: Vec<Element> = Vec::new();
+    // reads back a model written by write_to_json; the averaged bond orders are not part of a model and are dropped
+    pub fn read_from_json(path: &str, table: &ElementTable) -> Result<Model, LewisError> {
+        Model::from_json(&std::fs::read_to_string(path)?, table)
+    }
 
-    for element_name in element_names {
-        let file = File::open(file_path).unwrap();
-        let mut rdr = Reader::from_reader(file);
-        for result in rdr.records() {
-            let record = result.unwrap();
+    // the model in json written by to_json, checked for atoms that are unknown or listed twice, for bonds that
+    // point at missing atoms or disagree between the bonds array and the bonds_with entries and for hybrid orbitals
+    // that do not fit the atom's bonds and electrons
+    pub fn from_json(json: &str, table: &ElementTable) -> Result<Model, LewisError> {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(|e| LewisError::BadJson(e.to_string()))?;
+        match value.get("schema_version").and_then(|v| v.as_u64()) {
+            Some(version) if version == SCHEMA_VERSION as u64 => {}
+            Some(version) => return Err(LewisError::BadJson(
+                format!("schema_version {} is not supported, expected {}", version, SCHEMA_VERSION))),
+            None => return Err(LewisError::BadJson(
+                "no schema_version, the file was written by an older version and has to be solved again".to_string())),
+        }
+        let entry_model: EntryModel = serde_json::from_value(value).map_err(|e| LewisError::BadJson(e.to_string()))?;
+
+        let mut atoms = vec![];
+        for entry in &entry_model.atoms {
+            let symbol = entry.name.trim();
+            let element = table.by_symbol(symbol).ok_or_else(|| LewisError::UnknownElement(symbol.to_string()))?;
+            if atoms.iter().any(|atom: &Atom| atom.symbol() == symbol && atom.id == entry.id) {
+                return Err(LewisError::BadJson(format!("{}{} is listed twice", symbol, entry.id)));
+            }
+            if entry.unpaired > entry.lone {
+                return Err(LewisError::BadJson(format!("{}{} has more unpaired than nonbonding electrons", symbol, entry.id)));
+            }
+            atoms.push(Atom {
+                name: format!("{:<2}", symbol),
+                valence: entry.valence,
+                lone: entry.lone,
+                unpaired: entry.unpaired,
+                hybridization: entry.hybridization,
+                spd_orbitals: entry.spd_orbitals.clone(),
+                p_orbitals: entry.p_orbitals.clone(),
+                id: entry.id,
+                atomic_number: element.atomic_number(),
+                formal_charge: entry.formal_charge,
+                covalent_radius: element.atomic_radius().map(|r| r.round() as u32),
+            });
+        }
 
-            let mut symbol = record[1].replace("\t", "").replace(" ", "");
+        // the json has no charge of its own, the formal charges have to add up to it
+        let charge = entry_model.atoms.iter().map(|entry| entry.formal_charge).sum();
+        let mut model = Model::new(entry_model.name, charge, atoms);
+        for record in &entry_model.bonds {
+            let [a, b] = record.atoms;
+            if a >= model.atoms.len() || b >= model.atoms.len() || a == b {
+                return Err(LewisError::BadJson(format!("bond between atoms {} and {} of {}", a, b, model.atoms.len())));
+            }
+            if model.bond_between(AtomId(a), AtomId(b)).is_some() {
+                return Err(LewisError::BadJson(format!("bond between atoms {} and {} is listed twice", a, b)));
+            }
+            if record.sigma != 1 {
+                return Err(LewisError::BadJson(format!("bond between atoms {} and {} has {} sigma bonds", a, b, record.sigma)));
+            }
+            model.add_bond(AtomId(a), AtomId(b), record.pi);
+        }
 
-            if element_name == symbol {
-                if symbol.len() == 1 {
-                    symbol.push(' ');
-                }
+        // one hybrid orbital per sigma bond, lone pair and unpaired electron, and no more than sp3d5 has
+        for (i, atom) in model.atoms.iter().enumerate() {
+            let sigma = model.neighbors(AtomId(i)).map(|(_, bond)| bond.sigma as usize).sum::<usize>();
+            let expected = sigma + (atom.lone - atom.unpaired) as usize / 2 + atom.unpaired as usize;
+            if atom.spd_orbitals.len() != expected {
+                return Err(LewisError::BadJson(format!("{}{} has {} hybrid orbitals for {} sigma bonds, lone pairs and unpaired electrons",
+                    atom.symbol(), atom.id, atom.spd_orbitals.len(), expected)));
+            }
+            if Hybridization::from_steric_number(expected).is_err() {
+                return Err(LewisError::BadJson(format!("{}{} has {} hybrid orbitals, more than the 9 of sp3d5",
+                    atom.symbol(), atom.id, expected)));
+            }
+        }
 
-                let mut electron_config = record[5].split(' ').collect::<Vec<&str>>();
-                electron_config.remove(electron_config.len() - 1);
+        for (i, entry) in entry_model.atoms.iter().enumerate() {
+            let key = |name: &str, id: u32, bond_type: BondType| (name.trim().to_string(), id, bond_type as u8);
+            let mut written = entry.bonds_with.iter().map(|e| key(&e.name, e.id, e.bond_type)).collect::<Vec<_>>();
+            let mut expected = model.bond_entries(AtomId(i)).into_iter().map(|(a, b)| key(&a.name, a.id, b)).collect::<Vec<_>>();
+            written.sort();
+            expected.sort();
+            if written != expected {
+                return Err(LewisError::BadJson(format!("bonds_with of {}{} does not match the bonds array",
+                    entry.name.trim(), entry.id)));
+            }
+        }
 
-                let mut changed_electron_config: Vec<String> = electron_config.iter().map(|s| s.to_string()).collect();
+        let written_charges = model.atoms.iter().map(|atom| atom.formal_charge).collect::<Vec<_>>();
+        model.assign_formal_charges();
+        if let Some((atom, _)) = model.atoms.iter().zip(written_charges).find(|(atom, charge)| atom.formal_charge != *charge) {
+            return Err(LewisError::BadJson(format!("formal charge of {}{} does not match its electrons and bonds",
+                atom.symbol(), atom.id)));
+        }
+        Ok(model)
+    }
+}
 
-                if changed_electron_config.len() > 1 {
-                    changed_electron_config.remove(0);
-                    if changed_electron_config[0].contains("d") && changed_electron_config[1].contains("p") {
-                        changed_electron_config.remove(0);
-                    }
-                }
+// writes the models as one SDF file, e.g. every resonance structure of a molecule
+pub fn write_sdf(models: &[Model], path: String, version: MolfileVersion) -> std::io::Result<()> {
+    let mut sdf = String::new();
+    for model in models {
+        sdf.push_str(&model.to_molfile(version));
+        sdf.push_str("$$$$\n");
+    }
+    std::fs::write(path, sdf)
+}
 
-                let electronegativity = (&record[6].parse::<f64>().unwrap_or(0.0) * 100.0) as u32;
+// args[1] is the formula, which may carry its own charge (`SO4^2-`, `NH4+`, `[PO4]3-`, `CO3(2-)`);
+// args[2] is an optional explicit charge that has to agree with the one in the formula if both are given
+pub fn parse_input(args : &[String], format: InputFormat, table: &ElementTable) -> Result<ParsedMolecule, LewisError> {
+    let inputted_molecule = args.get(1).map(String::as_str).unwrap_or("");
+    let explicit_charge = match args.get(2) {
+        Some(c) => Some(c.parse::<i32>()
+            .map_err(|_| LewisError::BadCharge(format!("'{}' is not a whole number", c)))?),
+        None => None,
+    };
+    parse_compound(inputted_molecule, explicit_charge, format, table)
+}
 
-                let mut element_with_same_name_index = -1;
-                let mut element_with_same_name_id = -1;
+pub fn parse_compound(inputted_molecule: &str, explicit_charge: Option<i32>, format: InputFormat, table: &ElementTable) -> Result<ParsedMolecule, LewisError> {
+    let (parsed, skeleton, pi_bonds, kekule) = match format {
+        InputFormat::Formula => (parse_formula(inputted_molecule)?, None, vec![], vec![]),
+        InputFormat::Condensed => {
+            let condensed = parse_condensed(inputted_molecule)?;
+            (condensed.parsed, Some(condensed.bonds), vec![], vec![])
+        }
+        InputFormat::Smiles => {
+            let smiles = parse_smiles(inputted_molecule)?;
+            (smiles.parsed, Some(smiles.bonds), smiles.pi_bonds, smiles.kekule)
+        }
+    };
 
-                for i in 0..elements.iter().len() {
-                    if elements.get(i).unwrap().name == *symbol {
-                        element_with_same_name_index = i as i8;
-                        element_with_same_name_id = elements.get(i).unwrap().id as i8;
-                    }
-                }
+    let charge = match (parsed.charge, explicit_charge) {
+        (Some(written), Some(explicit)) if written != explicit => return Err(LewisError::BadCharge(
+            format!("charge {} written in {} conflicts with explicit charge {}", written, inputted_molecule, explicit))),
+        (written, explicit) => explicit.or(written).unwrap_or(0),
+    };
 
-                if element_with_same_name_index != -1 {
-                    elements.push(Element {name: symbol.clone(), electroneg: electronegativity, config: changed_electron_config.clone(), id: (element_with_same_name_id + 1) as u32 })
-                } else {
-                    elements.push(Element {name: symbol.clone(), electroneg: electronegativity, config: changed_electron_config.clone(), id: 0})
-                }
-            }
+    let fragments = parsed.fragments;
+    let mut element_names_counted = vec![];
+    for term in parsed.terms {
+        for _ in 0..term.count {
+            element_names_counted.push(term.symbol.clone());
         }
     }
 
-    elements
+    let elements = table.elements_for(&element_names_counted)?;
+
+    Ok(ParsedMolecule { name: format!("{}_{}", parsed.formula, charge), elements, charge, skeleton, pi_bonds, kekule, fragments })
 }
 
-pub fn parse_input(args : &[String]) -> ParsedMolecule {
-    let charge: i32 = args[2].clone().parse().unwrap();
 
-    let inputted_molecule = args[1].clone();
+// resonance structures listed at most, as large molecules can have a huge number of them
+const MAX_RESONANCE_MODELS: usize = 1000;
 
-    let mut counts: Vec<u32> = vec![];
+// bond order assignments tried at most per skeleton, the best ones found by then are kept
+const MAX_ASSIGNMENTS: usize = 200_000;
 
-    let mut element_names: Vec<String> = vec![];
-    let mut element_name = String::new();
+// what the solver needs to know about an atom to place bonds and electrons around it
+struct AtomRules {
+    valence: u32,
+    electroneg: u32,
+    hydrogen: bool,
+    // from the third period on an atom can hold more than eight electrons
+    can_expand: bool,
+    // bonds the atom makes when neutral, e.g. 4 for C and 2 for O
+    usual_bonds: u32,
+    // shared pairs (sigma and pi) the atom can take part in
+    max_bonds: u32,
+    // H and He only hold two electrons
+    shell: u32,
+}
 
-    for (i, c) in inputted_molecule.chars().enumerate() {
-        if c.is_alphabetic() {
-            if c.is_uppercase() {
-                if i != 0 && inputted_molecule.chars().nth(i - 1).unwrap().is_alphabetic() {
-                    counts.push(1);
-                }
-                if !element_name.is_empty() {
-                    element_names.push(element_name.clone());
-                    element_name.clear();
-                }
-            }
-            element_name.push(c);
-        } else if c.is_digit(10) {
-            if !element_name.is_empty() {
-                element_names.push(element_name.clone());
-                element_name.clear();
-            }
-            counts.push(c.to_digit(10).unwrap() as u32);
-        }
+impl AtomRules {
+    fn new(element: &Element) -> Result<Self, LewisError> {
+        let valence = element.valence_electrons()?;
+        let hydrogen = element.atomic_number == 1;
+        let can_expand = element.atomic_number > 10;
+        let usual_bonds = if hydrogen { 1 } else if valence < 4 { valence } else { 8 - valence.min(8) };
+        let max_bonds = if hydrogen { 1 } else if can_expand { valence.max(usual_bonds).max(4) } else { 4 };
+        let shell = if element.atomic_number <= 2 { 2 } else { 8 };
+        Ok(AtomRules { valence, electroneg: element.electroneg, hydrogen, can_expand, usual_bonds, max_bonds, shell })
     }
-    if !element_name.is_empty() {
-        element_names.push(element_name);
-        if inputted_molecule.chars().nth(inputted_molecule.chars().count() - 1).unwrap().is_alphabetic() {
-            counts.push(1);
-        }
+
+    fn max_electrons(&self) -> u32 {
+        if self.can_expand { 18 } else { self.shell }
     }
 
-    let mut element_names_counted = vec![];
+    // atoms with fewer than four valence electrons (Be, B, Al, ...) are stable without a full octet
+    fn needs_octet(&self) -> bool {
+        !self.hydrogen && self.valence >= 4
+    }
+}
 
-    let zipped = zip(element_names.iter(), counts.iter());
-    zipped.for_each(|(element, count)|
-        for _ in 0..*count {
-            element_names_counted.push(element.clone());
-        }
-    );
-
-    let elements = read_element_csv(
-        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("../data/data.csv").to_str().unwrap(),
-        element_names_counted
-    );
-
-    ParsedMolecule { name: format!("{}_{}", args[1].clone(), args[2]), elements, charge }
-}
-
-pub fn hybridize(valence: u8, which: Hybridization, central_atoms_bonds: Vec<(AtomRef, BondType)>) -> Vec<u8> {
-    let mut hybridized = vec![];
-    let mut bonds_count = central_atoms_bonds.len();
-    match which {
-        Hybridization::S => hybridized.push(0),
-        Hybridization::SP => hybridized = vec![0; 2],
-        Hybridization::SP2 => hybridized = vec![0; 3],
-        Hybridization::SP3 => hybridized = vec![0; 4],
-        Hybridization::SP3D => hybridized = vec![0; 5],
-        Hybridization::SP3D2 => hybridized = vec![0; 6],
-        Hybridization::SP3D3 => hybridized = vec![0; 7],
-        Hybridization::SP3D4 => hybridized = vec![0; 8],
-        Hybridization::SP3D5 => hybridized = vec![0; 9],
-    }
-
-    let length = hybridized.len();
-    let mut added_valence = valence;
-    for i in 0..valence {
-        if added_valence > 0 {
-            hybridized[i as usize % length] += 1;
-            added_valence -= 1;
-        } else {
-            break;
-        }
+// sigma bonds of the molecule, as pairs of indices into its atoms
+type Skeleton = Vec<(usize, usize)>;
+
+// hydrogens go where the most usual bonds are left, preferring the more electronegative atom on a tie
+// (so CH3OH gets its fourth H on O), and only past the usual bonds when nothing else is left (NH4+)
+fn attach_hydrogens(rules: &[AtomRules], heavy: &[usize], hydrogens: &[usize], skeleton: &mut Skeleton) -> Result<(), LewisError> {
+    let mut degree = vec![0u32; rules.len()];
+    for &(a, b) in skeleton.iter() {
+        degree[a] += 1;
+        degree[b] += 1;
     }
 
-    // add electrons from bonds
-    for i in 0..length {
-        if hybridized[i] == 1 && bonds_count > 0 {
-            hybridized[i] = 2;
-            bonds_count -= 1;
-        }
+    for &h in hydrogens {
+        let usual = heavy.iter().copied()
+            .filter(|&i| degree[i] < rules[i].usual_bonds)
+            .max_by(|&a, &b| (rules[a].usual_bonds - degree[a]).cmp(&(rules[b].usual_bonds - degree[b]))
+                .then(rules[a].electroneg.cmp(&rules[b].electroneg))
+                .then(b.cmp(&a)));
+        let target = usual.or_else(|| heavy.iter().copied()
+            .filter(|&i| degree[i] < rules[i].max_bonds)
+            .min_by(|&a, &b| rules[a].electroneg.cmp(&rules[b].electroneg).then(a.cmp(&b))))
+            .ok_or_else(|| LewisError::NoValidStructure("no atom has room left to bond another hydrogen".to_string()))?;
+        degree[target] += 1;
+        skeleton.push((target, h));
     }
-    hybridized
+    Ok(())
 }
 
-pub fn build_model(input_molecule: &ParsedMolecule) -> Model {
-    let mut charge = input_molecule.charge.abs();
-
-    let min_electroneg = input_molecule.elements.iter()
-        .filter(|x| x.name != "H ")
-        .min_by(|a, b| a.electroneg.cmp(&b.electroneg))
-        .unwrap_or(input_molecule.elements.iter().next().unwrap());
+// possible skeletons, one per element of the molecule that could form its backbone: a lone atom of that element
+// becomes the central atom, several of them a chain in the order they were written; the other non-hydrogen atoms
+// hang off the backbone atom with the most usual bonds left, and hydrogens are always terminal. Elements that make
+// two bonds or fewer (O, S, the halogens) only form a chain when no less electronegative element could be the
+// backbone instead, so oxyacids such as H2SO4 get a central atom rather than an O-O-O-O peroxide chain
+fn skeleton_candidates(elements: &[Element], rules: &[AtomRules]) -> Result<Vec<Skeleton>, LewisError> {
+    let (hydrogens, heavy): (Vec<usize>, Vec<usize>) = (0..elements.len()).partition(|&i| rules[i].hydrogen);
+
+    if heavy.is_empty() {
+        return match hydrogens.len() {
+            0 | 1 => Ok(vec![vec![]]),
+            2 => Ok(vec![vec![(hydrogens[0], hydrogens[1])]]),
+            n => Err(LewisError::NoValidStructure(format!("{} hydrogens cannot be connected, as each bonds only once", n))),
+        };
+    }
 
-    let bond_all_to_central = |atoms_count: usize, molecule: &mut Model, central_atom: AtomRef, bond_type: BondType| {
-        for i in 0..atoms_count {
-            let atomref = molecule.atoms.get(i).unwrap().clone();
-            let mut has_central = false;
-            for j in 0..molecule.bonds_with[i].len() {
-                if molecule.bonds_with[i][j].0 == central_atom {
-                    has_central = true;
-                }
-            }
-            match bond_type {
-                BondType::SIGMA => {
-                    if central_atom.clone() != atomref && !has_central {
-                        molecule.add_bond(atomref, central_atom.clone(), bond_type);
-                    }
-                }
-                BondType::PI => {
-                    if central_atom.clone() != atomref {
-                        molecule.add_bond(atomref, central_atom.clone(), bond_type);
-                    }
-                }
-            }
+    // least electronegative elements first, so ties between skeletons go to the usual central atom
+    let backbone_elements = heavy.iter().map(|&i| elements[i].atomic_number).unique()
+        .sorted_by_key(|&number| rules[heavy.iter().copied().find(|&i| elements[i].atomic_number == number).unwrap_or(0)].electroneg)
+        .collect::<Vec<_>>();
+
+    let mut candidates: Vec<Skeleton> = vec![];
+    let mut last_error = None;
+    for number in backbone_elements {
+        let backbone = heavy.iter().copied().filter(|&i| elements[i].atomic_number == number).collect::<Vec<_>>();
+        if backbone.len() > 1 && rules[backbone[0]].usual_bonds <= 2
+            && heavy.iter().any(|&i| rules[i].electroneg < rules[backbone[0]].electroneg) {
+            continue;
+        }
+        let mut skeleton: Skeleton = backbone.iter().copied().tuple_windows().collect();
 
+        let mut degree = vec![0u32; elements.len()];
+        for &(a, b) in skeleton.iter() {
+            degree[a] += 1;
+            degree[b] += 1;
+        }
+        for &leaf in heavy.iter().filter(|i| !backbone.contains(i)) {
+            let free = |i: usize| rules[i].usual_bonds as i64 - degree[i] as i64;
+            let target = backbone.iter().copied()
+                .max_by(|&a, &b| free(a).cmp(&free(b)).then(b.cmp(&a)))
+                .unwrap_or(backbone[0]);
+            degree[target] += 1;
+            degree[leaf] += 1;
+            skeleton.push((target, leaf));
         }
-    };
 
-    let mut central_atom : AtomRef = Rc::new(RefCell::new(Atom {
-        name: "".to_string(),
-        valence: 0,
-        lone: 0,
-        hybridization: Hybridization::S,
-        spd_orbitals: vec![],
-        p_orbitals: vec![],
-        id: 0,
-    }));
-
-    let atoms_count = input_molecule.elements.len();
-
-    let mut atoms_vec: Vec<AtomRef> = vec![];
-    let mut central_atom_index = 0;
-    for j in 0..input_molecule.elements.iter().len() {
-
-        let element = input_molecule.elements[j].clone();
-        let mut valence_count = 0;
-
-        if element.config.len() < 3 {
-            let s_orbital_count = element.config[0].chars().last().unwrap().to_digit(10).unwrap();
-            let p_orbital_count = element.config.get(1).unwrap_or(&"0".to_string()).chars().last().unwrap().to_digit(10).unwrap();
-            valence_count += s_orbital_count + p_orbital_count;
-        } else {
-            let s_orbital_count = element.config[1].chars().last().unwrap().to_digit(10).unwrap();
-            let p_orbital_count = element.config[2].chars().last().unwrap().to_digit(10).unwrap();
-            valence_count += s_orbital_count + p_orbital_count;
+        match attach_hydrogens(rules, &heavy, &hydrogens, &mut skeleton) {
+            Ok(()) if !candidates.contains(&skeleton) => candidates.push(skeleton),
+            Ok(()) => {}
+            Err(e) => last_error = Some(e),
         }
+    }
 
-        let atom = Atom {
-            name: element.name.clone(),
-            valence: valence_count as u32,
-            lone: valence_count as u32,
-            hybridization: match valence_count {
-                0 | 1 => Hybridization::S,
-                2 => Hybridization::SP,
-                3 => Hybridization::SP2,
-                _ => Hybridization::SP3,
-            },
-            spd_orbitals: match valence_count {
-                0 | 1 => hybridize(valence_count as u8, Hybridization::S, vec![]),
-                2 => hybridize(valence_count as u8, Hybridization::SP, vec![]),
-                3 => hybridize(valence_count as u8, Hybridization::SP2, vec![]),
-                _ => hybridize(valence_count as u8, Hybridization::SP3, vec![]),
-            },
-            p_orbitals: vec![],
-            id: element.id
-        };
+    match (candidates.is_empty(), last_error) {
+        (true, Some(e)) => Err(e),
+        _ => Ok(candidates),
+    }
+}
+
+// a skeleton given by the user has to connect every atom without giving one more bonds than it can make
+fn check_skeleton(elements: &[Element], rules: &[AtomRules], skeleton: &Skeleton) -> Result<(), LewisError> {
+    let label = |i: usize| format!("{}{}", elements[i].name.trim(), elements[i].id);
 
-        let atomref = Rc::new(RefCell::new(atom));
-        if min_electroneg.name == element.name && min_electroneg.id == element.id {
-            central_atom = atomref.clone();
-            central_atom_index = j;
+    let mut degree = vec![0u32; elements.len()];
+    for &(a, b) in skeleton.iter() {
+        degree[a] += 1;
+        degree[b] += 1;
+    }
+    if let Some(i) = (0..elements.len()).find(|&i| degree[i] > rules[i].max_bonds) {
+        return Err(LewisError::BadConnectivity(
+            format!("{} has {} bonds but can make at most {}", label(i), degree[i], rules[i].max_bonds)));
+    }
+
+    let mut reached = vec![false; elements.len()];
+    let mut stack = vec![0];
+    reached[0] = true;
+    while let Some(i) = stack.pop() {
+        for &(a, b) in skeleton.iter() {
+            let other = if a == i { b } else if b == i { a } else { continue };
+            if !reached[other] {
+                reached[other] = true;
+                stack.push(other);
+            }
         }
-        atoms_vec.push(atomref.clone());
     }
+    match reached.iter().position(|&r| !r) {
+        Some(i) => Err(LewisError::BadConnectivity(format!("{} is not connected to {}", label(i), label(0)))),
+        None => Ok(()),
+    }
+}
+
+// alkali and alkaline earth metals (apart from Be, which shares its electrons as in BeCl2) form ions with the other
+// atoms, and d and f block metals complexes, neither of which a Lewis structure describes; a lone atom is fine
+fn ionic_metal(elements: &[Element]) -> Option<&Element> {
+    if elements.len() < 2 {
+        return None;
+    }
+    elements.iter().find(|element| element.atomic_number != 4 && matches!(element.group_block(),
+        Some(GroupBlock::AlkaliMetal | GroupBlock::AlkalineEarthMetal | GroupBlock::TransitionMetal
+            | GroupBlock::Lanthanoid | GroupBlock::Actinoid)))
+}
+
+// one way of placing the electrons over a skeleton: pi bonds on top of each sigma bond and nonbonding electrons
+// of each atom
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Placement {
+    pi: Vec<u32>,
+    lone: Vec<u32>,
+    // atom left with the odd electron when the molecule has an odd number of them
+    unpaired: Option<usize>,
+}
 
-    let mut molecule = Model::new(input_molecule.name.clone(), atoms_vec.clone());
+// missing octet electrons, then the sum of |formal charge|, then formal charge weighted by electronegativity
+// (so negative charges sit on electronegative atoms), then electrons beyond an octet, then the electronegativity
+// of the atom with the odd electron (so it sits on the least electronegative one); lower is better
+type Score = (u32, u32, i64, u32, u32);
+
+// tries every bond order on the bonds between non-hydrogen atoms and fills in lone pairs for each
+struct Search<'a> {
+    rules: &'a [AtomRules],
+    skeleton: &'a Skeleton,
+    pi_bonds: Vec<usize>,
+    // lone pairs complete the octets of the most electronegative atoms first
+    fill_order: Vec<usize>,
+    // electrons left after that go to atoms that can hold more than eight, central ones first
+    expand_order: Vec<usize>,
+    electrons: u32,
+    // pi bonds allowed on each bond of the skeleton
+    pi_ranges: Vec<RangeInclusive<u32>>,
+    // pi bonds tried first on each bond, if known
+    first: Vec<Option<u32>>,
+    bonds: Vec<u32>,
+    pi: Vec<u32>,
+    tried: usize,
+    // whether MAX_ASSIGNMENTS was reached before every assignment was tried
+    truncated: bool,
+    best: Option<Score>,
+    placements: Vec<Placement>,
+}
 
-    bond_all_to_central(atoms_count, &mut molecule, central_atom.clone(), BondType::SIGMA);
+impl<'a> Search<'a> {
+    fn new(rules: &'a [AtomRules], skeleton: &'a Skeleton, pi_ranges: &[RangeInclusive<u32>], first: &[u32], electrons: u32) -> Self {
+        let mut bonds = vec![0u32; rules.len()];
+        for &(a, b) in skeleton.iter() {
+            bonds[a] += 1;
+            bonds[b] += 1;
+        }
+        let degree = bonds.clone();
+        let pi_bonds = (0..skeleton.len())
+            .filter(|&e| !rules[skeleton[e].0].hydrogen && !rules[skeleton[e].1].hydrogen)
+            .collect();
+        let fill_order = (0..rules.len())
+            .sorted_by(|&a, &b| rules[b].electroneg.cmp(&rules[a].electroneg).then(degree[a].cmp(&degree[b])))
+            .collect();
+        let expand_order = (0..rules.len()).filter(|&i| rules[i].can_expand)
+            .sorted_by(|&a, &b| degree[b].cmp(&degree[a]).then(rules[a].electroneg.cmp(&rules[b].electroneg)))
+            .collect();
+        Search {
+            rules,
+            skeleton,
+            pi_bonds,
+            fill_order,
+            expand_order,
+            electrons,
+            pi_ranges: (0..skeleton.len()).map(|e| pi_ranges.get(e).cloned().unwrap_or(0..=2)).collect(),
+            first: (0..skeleton.len()).map(|e| first.get(e).copied()).collect(),
+            bonds,
+            pi: vec![0; skeleton.len()],
+            tried: 0,
+            truncated: false,
+            best: None,
+            placements: vec![],
+        }
+    }
 
-    dbg!(&molecule.atoms);
-    dbg!(&molecule.bonds_with);
+    // tries the Kekulé structure first if there is one, otherwise the most pi bonds first, so that full octets are
+    // found early and prune the rest of the search
+    fn run(&mut self, k: usize, used: u32) {
+        if self.tried >= MAX_ASSIGNMENTS {
+            self.truncated = true;
+            return;
+        }
+        if self.best.is_some_and(|best| self.missing_octets_bound(k, used) > best.0) {
+            return;
+        }
+        if k == self.pi_bonds.len() {
+            self.tried += 1;
+            self.evaluate(self.electrons - used);
+            return;
+        }
 
-    // if all atoms are bonded but are missing electrons, create double/triple bonds
-    // else hybridize further to allow for more bonding slots
+        let bond = self.pi_bonds[k];
+        let (a, b) = self.skeleton[bond];
+        let first = self.first[bond].filter(|extra| self.pi_ranges[bond].contains(extra));
+        for extra in first.into_iter().chain(self.pi_ranges[bond].clone().rev().filter(|&extra| Some(extra) != first)) {
+            if self.bonds[a] + extra > self.rules[a].max_bonds || self.bonds[b] + extra > self.rules[b].max_bonds
+                || used + 2 * extra > self.electrons {
+                continue;
+            }
+            self.pi[bond] = extra;
+            self.bonds[a] += extra;
+            self.bonds[b] += extra;
+            self.run(k + 1, used + 2 * extra);
+            self.bonds[a] -= extra;
+            self.bonds[b] -= extra;
+            self.pi[bond] = 0;
+        }
+    }
 
-    // if not all atoms are bonded, hybridize central atom further, try bonding again. keep going until all atoms are bonded to central atom
-    while !molecule.bonds_with.iter().all(|x| x.len() > 0) {
-        let next_hyb = central_atom.borrow().clone().hybridization.next().unwrap();
-        let new_spds = hybridize(central_atom.borrow().clone().valence as u8, next_hyb.clone(), molecule.bonds_with[central_atom_index].clone()).clone();
-        central_atom.borrow_mut().spd_orbitals = new_spds;
-        central_atom.borrow_mut().hybridization = next_hyb;
+    // lower bound on the missing octet electrons (score.0) of any assignment that keeps the pi bonds of the first
+    // `k` bonds: every pi bond still to come fills two electrons of each of its atoms for two electrons, every other
+    // electron fills one
+    fn missing_octets_bound(&self, k: usize, used: u32) -> u32 {
+        let mut room = (0..self.rules.len()).map(|i| self.rules[i].max_bonds.saturating_sub(self.bonds[i])).collect::<Vec<_>>();
+        let mut reachable = vec![0u32; self.rules.len()];
+        for &bond in &self.pi_bonds[k..] {
+            let (a, b) = self.skeleton[bond];
+            let extra = (*self.pi_ranges[bond].end()).min(room[a]).min(room[b]);
+            reachable[a] += extra;
+            reachable[b] += extra;
+        }
+        let (mut short, mut by_pi) = (0, 0);
+        for i in (0..self.rules.len()).filter(|&i| self.rules[i].needs_octet()) {
+            let missing = 8u32.saturating_sub(2 * self.bonds[i]);
+            room[i] = room[i].min(reachable[i]);
+            short += missing;
+            by_pi += missing.min(2 * room[i]);
+        }
+        let remaining = self.electrons - used;
+        let filled = by_pi.min(2 * remaining) + remaining - remaining.min(by_pi / 2);
+        short.saturating_sub(filled)
+    }
 
-        bond_all_to_central(atoms_count, &mut molecule, central_atom.clone(), BondType::SIGMA);
+    fn evaluate(&mut self, remaining: u32) {
+        if remaining.is_multiple_of(2) {
+            self.place(remaining, None);
+        } else {
+            // a radical: the odd electron is tried on every atom, and the scores pick the best one
+            for i in 0..self.rules.len() {
+                self.place(remaining, Some(i));
+            }
+        }
     }
 
-    // once all atoms are bonded to central atom, take out hybridized orbitals with only one electron and make them p orbitals
-    for i in 0..atoms_count {
-        let mut spds = molecule.atoms[i].borrow().clone().spd_orbitals;
-        let mut p_orbs = vec![];
-        for j in 0..spds.len() {
-            if spds[j] == 1 {
-                spds[j] = 0;
-                p_orbs.push(1);
+    fn place(&mut self, mut remaining: u32, unpaired: Option<usize>) {
+        let rules = self.rules;
+        let mut lone = vec![0u32; rules.len()];
+        if let Some(i) = unpaired {
+            if 2 * self.bonds[i] + 1 > rules[i].max_electrons() {
+                return;
             }
+            lone[i] = 1;
+            remaining -= 1;
+        }
+        for &i in self.fill_order.iter() {
+            let give = rules[i].shell.saturating_sub(2 * self.bonds[i] + lone[i]).min(remaining) & !1;
+            lone[i] += give;
+            remaining -= give;
         }
-        let mut atom = molecule.atoms[i].borrow_mut();
-        atom.spd_orbitals = spds.iter().filter(|&&x| x != 0).copied().collect();
-        atom.p_orbitals = p_orbs.clone();
-        let mut new_hy = atom.hybridization.clone();
-        for _ in 0..p_orbs.len() {
-            new_hy = new_hy.before().unwrap();
-        }
-        atom.hybridization = new_hy;
-    }
-
-    // bond each extra p orbital to a central p orbital if other atoms have p orbitals
-    if molecule.bonds_with[0].iter().any(|a| a.0.borrow().p_orbitals.len() > 0) {
-        // bonded p orbitals (pi bonds) shown by a "ghost" electron in p_orbitals array to make it 2
-        while central_atom.borrow().p_orbitals.contains(&1u8) {
-            // search for atom that has an empty slot in p_orbitals, meaning it only has 1 electron
-            bond_all_to_central(atoms_count, &mut molecule, central_atom.clone(), BondType::PI);
-        }
-    }
-
-    // if excess p_orbitals, check charge
-    // if charge is 0, "give" p orbital to central atom
-    // if charge is not 0, "give" p orbital back to corresponding atom and add missing number of electrons
-
-    if charge == 0 {
-        while molecule.atoms.iter().filter(|x| x.borrow().p_orbitals.contains(&1)).collect::<Vec<_>>().len() > 0 {
-            if let Some(index) = molecule.atoms.iter().position(|x| x.borrow().p_orbitals.contains(&1u8)) {
-                let mut modified_outer_atom = molecule.atoms[index].borrow_mut();
-                let mut modified_central_atom = central_atom.borrow_mut();
-                let new_hy_central = modified_central_atom.clone().hybridization.before().unwrap();
-                let new_hy_outer = modified_outer_atom.clone().hybridization.next().unwrap();
-
-                modified_outer_atom.p_orbitals.remove(0);
-                modified_outer_atom.spd_orbitals.push(2);
-                modified_outer_atom.hybridization = new_hy_outer;
-                modified_outer_atom.lone += 1;
-                modified_central_atom.spd_orbitals.pop();
-                modified_central_atom.p_orbitals.push(1);
-                modified_central_atom.hybridization = new_hy_central;
-                modified_central_atom.lone -= 1;
+        for &i in self.expand_order.iter() {
+            let give = rules[i].max_electrons().saturating_sub(2 * self.bonds[i] + lone[i]).min(remaining) & !1;
+            lone[i] += give;
+            remaining -= give;
+        }
+        if remaining > 0 {
+            return;
+        }
+
+        let mut score: Score = (0, 0, 0, 0, unpaired.map_or(0, |i| rules[i].electroneg));
+        for (i, rule) in rules.iter().enumerate() {
+            if rule.needs_octet() {
+                score.0 += 8u32.saturating_sub(2 * self.bonds[i] + lone[i]);
             }
-            bond_all_to_central(atoms_count, &mut molecule, central_atom.clone(), BondType::PI);
-        }
-    } else {
-        while molecule.atoms.iter().filter(|x| x.borrow().p_orbitals.contains(&1u8)).collect::<Vec<_>>().len() > 0 {
-            if charge > 0 {
-                // change unbonded ps to lone pairs
-                if let Some(index) = molecule.atoms.iter().position(|x| x.borrow().p_orbitals.contains(&1u8)) {
-                    let mut modified_outer_atom = molecule.atoms[index].borrow_mut();
-                    let new_hy = modified_outer_atom.clone().hybridization.next().unwrap();
-                    modified_outer_atom.p_orbitals.remove(0);
-                    modified_outer_atom.spd_orbitals.push(2);
-                    modified_outer_atom.hybridization = new_hy;
-                    modified_outer_atom.lone += 1;
-                    charge -= 1;
-                }
-            } else {
-                if let Some(index) = molecule.atoms.iter().position(|x| x.borrow().p_orbitals.contains(&1u8)) {
-                    let mut modified_outer_atom = molecule.atoms[index].borrow_mut();
-                    let mut modified_central_atom = central_atom.borrow_mut();
-                    let new_hy_central = modified_central_atom.clone().hybridization.before().unwrap();
-                    modified_central_atom.spd_orbitals.pop();
-                    modified_central_atom.p_orbitals.push(1);
-                    modified_central_atom.p_orbitals.push(1);
-                    modified_central_atom.hybridization = new_hy_central;
-                    modified_outer_atom.p_orbitals.remove(0);
-                    modified_outer_atom.p_orbitals.push(1);
+            let formal_charge = rule.valence as i64 - lone[i] as i64 - self.bonds[i] as i64;
+            score.1 += formal_charge.unsigned_abs() as u32;
+            score.2 += formal_charge * rule.electroneg as i64;
+            score.3 += (2 * self.bonds[i] + lone[i]).saturating_sub(8);
+        }
+
+        let placement = Placement { pi: self.pi.clone(), lone, unpaired };
+        match self.best {
+            Some(best) if score > best => {}
+            Some(best) if score == best => {
+                // a structure is listed once however often it is reached
+                if self.placements.len() < MAX_RESONANCE_MODELS && !self.placements.contains(&placement) {
+                    self.placements.push(placement);
                 }
-                bond_all_to_central(atoms_count, &mut molecule, central_atom.clone(), BondType::PI);
             }
+            _ => {
+                self.best = Some(score);
+                self.placements = vec![placement];
+            }
+        }
+    }
+}
+
+// best skeleton of the molecule with every best placement of its electrons on it
+struct Solution {
+    rules: Vec<AtomRules>,
+    skeleton: Skeleton,
+    placements: Vec<Placement>,
+}
+
+fn solve(input_molecule: &ParsedMolecule) -> Result<Solution, LewisError> {
+    let elements = &input_molecule.elements;
+    if elements.is_empty() {
+        return Err(LewisError::NoValidStructure("molecule has no atoms".to_string()));
+    }
+    if input_molecule.fragments > 1 {
+        return Err(LewisError::NoValidStructure(format!("{} is made of {} separate molecules or ions, which have to be \
+            solved one at a time", input_molecule.name, input_molecule.fragments)));
+    }
+    if let Some(metal) = ionic_metal(elements) {
+        return Err(LewisError::NoValidStructure(format!("{} is an ionic compound or metal complex of {}, which shared \
+            pairs do not describe", input_molecule.name, metal.symbol())));
+    }
+    let rules = elements.iter().map(AtomRules::new).collect::<Result<Vec<_>, _>>()?;
+
+    let total = rules.iter().map(|r| r.valence as i64).sum::<i64>() - input_molecule.charge as i64;
+    if total < 0 {
+        return Err(LewisError::NoValidStructure(format!("a charge of {} takes more electrons than the molecule has", input_molecule.charge)));
+    }
+
+    let candidates = match &input_molecule.skeleton {
+        Some(skeleton) => {
+            // hydrogens left out of the given bonds are attached the way skeleton_candidates attaches them
+            let mut skeleton = skeleton.clone();
+            let (hydrogens, heavy): (Vec<usize>, Vec<usize>) = (0..elements.len()).partition(|&i| rules[i].hydrogen);
+            let unbonded = hydrogens.into_iter().filter(|&h| !skeleton.iter().any(|&(a, b)| a == h || b == h)).collect::<Vec<_>>();
+            if !heavy.is_empty() {
+                attach_hydrogens(&rules, &heavy, &unbonded, &mut skeleton)?;
+            }
+            check_skeleton(elements, &rules, &skeleton)?;
+            vec![skeleton]
+        }
+        None => skeleton_candidates(elements, &rules)?,
+    };
 
+    let mut best: Option<(Score, Skeleton, Vec<Placement>)> = None;
+    for skeleton in candidates {
+        let Ok(electrons) = u32::try_from(total - 2 * skeleton.len() as i64) else { continue };
+
+        let mut search = Search::new(&rules, &skeleton, &input_molecule.pi_bonds, &input_molecule.kekule, electrons);
+        if search.bonds.iter().zip(rules.iter()).any(|(&bonds, rule)| bonds > rule.max_bonds) {
+            continue;
+        }
+        search.run(0, 0);
+        if search.truncated {
+            return Err(LewisError::SearchLimit(MAX_ASSIGNMENTS));
         }
+        // back in the order of fewest pi bonds on the first bonds, as the structures are listed
+        search.placements.sort_by(|a, b| a.pi.cmp(&b.pi).then(a.unpaired.cmp(&b.unpaired)));
+        let (Some(score), placements) = (search.best, search.placements) else { continue };
+        if best.as_ref().is_none_or(|(best_score, _, _)| score < *best_score) {
+            best = Some((score, skeleton, placements));
+        }
+    }
+
+    let (_, skeleton, placements) = best.ok_or_else(|| LewisError::NoValidStructure(
+        "no arrangement of the atoms has room for every bond and electron".to_string()))?;
+    Ok(Solution { rules, skeleton, placements })
+}
+
+// turns a placement into atoms: every sigma bond, lone pair and unpaired electron takes one hybrid orbital, every pi
+// bond a p orbital
+fn model_from_placement(input_molecule: &ParsedMolecule, solution: &Solution, placement: &Placement) -> Result<Model, LewisError> {
+    let mut sigma_bonds = vec![0usize; solution.rules.len()];
+    let mut pi_bonds = vec![0usize; solution.rules.len()];
+    for (&(a, b), &pi) in solution.skeleton.iter().zip(placement.pi.iter()) {
+        sigma_bonds[a] += 1;
+        sigma_bonds[b] += 1;
+        pi_bonds[a] += pi as usize;
+        pi_bonds[b] += pi as usize;
+    }
+
+    let mut atoms = vec![];
+    for (i, element) in input_molecule.elements.iter().enumerate() {
+        let unpaired = u32::from(placement.unpaired == Some(i));
+        let pairs = sigma_bonds[i] + (placement.lone[i] - unpaired) as usize / 2;
+        let mut spd_orbitals = vec![2; pairs];
+        spd_orbitals.extend(vec![1; unpaired as usize]);
+        atoms.push(Atom {
+            name: element.name.clone(),
+            valence: solution.rules[i].valence,
+            lone: placement.lone[i],
+            unpaired,
+            hybridization: Hybridization::from_steric_number(spd_orbitals.len())?,
+            spd_orbitals,
+            p_orbitals: vec![2; pi_bonds[i]],
+            id: element.id,
+            atomic_number: element.atomic_number,
+            formal_charge: 0,
+            covalent_radius: element.atomic_radius.map(|r| r.round() as u32),
+        });
     }
 
-    molecule
+    let mut model = Model::new(input_molecule.name.clone(), input_molecule.charge, atoms);
+    for (&(a, b), &pi) in solution.skeleton.iter().zip(placement.pi.iter()) {
+        model.add_bond(AtomId(a), AtomId(b), pi);
+    }
+
+    model.assign_formal_charges();
+    let total_formal_charge = model.total_formal_charge();
+    if total_formal_charge != input_molecule.charge {
+        return Err(LewisError::FormalChargeMismatch { expected: input_molecule.charge, found: total_formal_charge });
+    }
+    // a model whose electrons, charges, orbitals or bonds do not add up is a bug in the solver, so debug builds stop
+    // at it; octets are left to validate, as some inputs such as CH2 cannot have them
+    if cfg!(debug_assertions) {
+        let inconsistencies = model.validate().into_iter().filter(Violation::is_inconsistency).collect::<Vec<_>>();
+        assert!(inconsistencies.is_empty(), "inconsistent structure for {}: {}", model.name,
+            inconsistencies.iter().map(ToString::to_string).join(", "));
+    }
+    Ok(model)
+}
+
+pub fn build_model(input_molecule: &ParsedMolecule) -> Result<Model, LewisError> {
+    let solution = solve(input_molecule)?;
+    model_from_placement(input_molecule, &solution, &solution.placements[0])
+}
+
+// all resonance structures of the molecule: every placement of bonds and lone pairs on the skeleton of build_model
+// that scores as well as the one build_model picks, each once; structures that only differ by a symmetry of the
+// molecule, like the three of CO3 2- or the two Kekulé structures of benzene, are distinct resonance structures and
+// all listed, even though their canonical SMILES are the same
+pub fn build_resonance_models(input_molecule: &ParsedMolecule) -> Result<Vec<Model>, LewisError> {
+    let solution = solve(input_molecule)?;
+    solution.placements.iter()
+        .map(|placement| model_from_placement(input_molecule, &solution, placement))
+        .collect()
 }
@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+use crate::diagram::{charge_text, direction_between, grid_positions, Depth, DiagramAtom, SLOTS, STEPS};
+
+// distance between neighboring atoms, in pixels
+const SPACING: f64 = 60.0;
+// bonds stop this far from the atoms so that they do not run into the symbols
+const LABEL_GAP: f64 = 14.0;
+// distance of lone pair dots from their atom
+const DOT_DISTANCE: f64 = 17.0;
+const MARGIN: f64 = 40.0;
+// typographic minus sign for negative charges
+const MINUS: char = '\u{2212}';
+
+type Point = (f64, f64);
+
+fn unit((x, y): Point) -> Point {
+    let length = x.hypot(y);
+    if length < 1e-12 { (0.0, 0.0) } else { (x / length, y / length) }
+}
+
+// unit vector of a grid direction, with y growing downwards as in svg
+fn direction_vector(d: usize) -> Point {
+    let (dr, dc) = STEPS[d];
+    unit((dc as f64, dr as f64))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// the Lewis diagram as an svg image on the same grid as the ascii one: element symbols, bonds as one to three
+// lines, lone pairs and unpaired electrons as dots and formal charges in a free corner of their atom; with `wedges`
+// the bonds that leave the page in a perspective drawing of each shape become wedges (towards the viewer) and
+// hashed wedges (away from the viewer)
+pub fn render_svg(title: &str, atoms: &[DiagramAtom], bonds: &[(usize, usize, u32)], wedges: bool) -> String {
+    let layout = grid_positions(atoms, bonds);
+    let occupied = layout.cells.iter().copied().collect::<HashSet<_>>();
+    let center = |i: usize| {
+        let (row, column) = layout.cells[i];
+        (column as f64 * SPACING, row as f64 * SPACING)
+    };
+
+    let mut lines = String::new();
+    let mut shapes = String::new();
+    // directions already taken by bonds around each atom
+    let mut sides: Vec<Vec<usize>> = vec![vec![]; atoms.len()];
+    for &(a, b, order) in bonds {
+        let ((ax, ay), (bx, by)) = (center(a), center(b));
+        let along = unit((bx - ax, by - ay));
+        let d = direction_between(layout.cells[a], layout.cells[b]).unwrap_or_else(|| {
+            (0..8).max_by(|&x, &y| {
+                let (vx, vy) = (direction_vector(x), direction_vector(y));
+                (vx.0 * along.0 + vx.1 * along.1).total_cmp(&(vy.0 * along.0 + vy.1 * along.1))
+            }).unwrap_or(0)
+        });
+        sides[a].push(d);
+        sides[b].push((d + 4) % 8);
+
+        let (from, to) = ((ax + along.0 * LABEL_GAP, ay + along.1 * LABEL_GAP), (bx - along.0 * LABEL_GAP, by - along.1 * LABEL_GAP));
+        let across = (-along.1, along.0);
+        let depth = layout.wedges.iter().find(|&&(x, y, _)| (x, y) == (a, b) || (x, y) == (b, a)).filter(|_| wedges && order == 1);
+        match depth {
+            Some(&(narrow, _, depth)) => {
+                let (tip, base) = if narrow == a { (from, to) } else { (to, from) };
+                let half = |t: f64| (across.0 * 5.0 * t, across.1 * 5.0 * t);
+                if depth == Depth::Front {
+                    let (hx, hy) = half(1.0);
+                    shapes.push_str(&format!("  <polygon points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\"/>\n",
+                        tip.0, tip.1, base.0 + hx, base.1 + hy, base.0 - hx, base.1 - hy));
+                } else {
+                    // hashes widening from the narrow end
+                    for k in 1..=6 {
+                        let t = k as f64 / 6.0;
+                        let (px, py) = (tip.0 + (base.0 - tip.0) * t, tip.1 + (base.1 - tip.1) * t);
+                        let (hx, hy) = half(t);
+                        lines.push_str(&format!("  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"/>\n",
+                            px + hx, py + hy, px - hx, py - hy));
+                    }
+                }
+            }
+            None => {
+                let offsets: &[f64] = match order {
+                    2 => &[-3.5, 3.5],
+                    3 => &[-5.0, 0.0, 5.0],
+                    _ => &[0.0],
+                };
+                for offset in offsets {
+                    let (ox, oy) = (across.0 * offset, across.1 * offset);
+                    lines.push_str(&format!("  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"/>\n",
+                        from.0 + ox, from.1 + oy, to.0 + ox, to.1 + oy));
+                }
+            }
+        }
+    }
+
+    let mut labels = String::new();
+    for (i, atom) in atoms.iter().enumerate() {
+        let (x, y) = center(i);
+        labels.push_str(&format!("  <text x=\"{:.1}\" y=\"{:.1}\">{}</text>\n", x, y, escape(atom.symbol.trim())));
+
+        // free sides, those facing other atoms and the corners last
+        let mut free = SLOTS.iter().copied().filter(|d| !sides[i].contains(d)).collect::<Vec<_>>();
+        let cell = layout.cells[i];
+        free.sort_by_key(|&d| {
+            let (dr, dc) = STEPS[d];
+            (occupied.contains(&(cell.0 + dr, cell.1 + dc)), d % 2 == 1)
+        });
+        let mut free = free.into_iter();
+        let mut groups = vec![2; (atom.lone / 2) as usize];
+        if atom.lone % 2 == 1 {
+            groups.push(1);
+        }
+        for count in groups {
+            let Some(d) = free.next() else { break };
+            let (ux, uy) = direction_vector(d);
+            let (px, py) = (x + ux * DOT_DISTANCE, y + uy * DOT_DISTANCE);
+            let spread: &[f64] = if count == 2 { &[-4.0, 4.0] } else { &[0.0] };
+            for s in spread {
+                shapes.push_str(&format!("  <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"2.2\"/>\n", px - uy * s, py + ux * s));
+            }
+        }
+        if atom.charge != 0 {
+            let corner = free.find(|d| d % 2 == 1).unwrap_or(1);
+            let (ux, uy) = direction_vector(corner);
+            labels.push_str(&format!("  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"13\">{}</text>\n",
+                x + ux * 24.0, y + uy * 24.0, charge_text(atom.charge, MINUS)));
+        }
+    }
+
+    let points = (0..atoms.len()).map(center).collect::<Vec<_>>();
+    let left = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min) - MARGIN;
+    let top = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min) - MARGIN;
+    let width = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max) + MARGIN - left;
+    let height = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max) + MARGIN - top;
+
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"{:.1} {:.1} {:.1} {:.1}\">\n",
+        width, height, left, top, width, height);
+    svg.push_str(&format!("<title>{}</title>\n", escape(title)));
+    svg.push_str(&format!("<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"white\"/>\n", left, top, width, height));
+    svg.push_str(&format!("<g stroke=\"black\" stroke-width=\"1.5\" stroke-linecap=\"round\">\n{}</g>\n", lines));
+    svg.push_str(&format!("<g fill=\"black\">\n{}</g>\n", shapes));
+    svg.push_str(&format!("<g font-family=\"sans-serif\" font-size=\"20\" text-anchor=\"middle\" dominant-baseline=\"central\">\n{}</g>\n", labels));
+    svg.push_str("</svg>\n");
+    svg
+}
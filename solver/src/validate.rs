@@ -0,0 +1,132 @@
+use std::fmt;
+use crate::solve::{Atom, AtomId, Model};
+
+// something wrong with a finished Lewis structure, see Model::validate; atoms are named by symbol and id, e.g. `O1`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    // electrons in lone pairs and bonds do not add up to the valence electrons minus the charge
+    ElectronCount { expected: u32, found: u32 },
+    // formal charges do not add up to the charge of the molecule
+    TotalCharge { expected: i32, found: i32 },
+    // formal charge that does not follow from the atom's electrons and bonds
+    FormalCharge { atom: String, expected: i32, found: i32 },
+    // more electrons around an atom than its shell holds, 2 for H and He and 8 for the second period
+    ShellExceeded { atom: String, electrons: u32, limit: u32 },
+    // second-period atom with four or more valence electrons and less than an octet, not counting a radical's
+    IncompleteOctet { atom: String, electrons: u32 },
+    // odd number of nonbonding electrons of which none is marked unpaired, or the other way around
+    UnpairedElectrons { atom: String, lone: u32, unpaired: u32 },
+    // hybrid or p orbital with more than two electrons
+    OrbitalOverfilled { atom: String, electrons: u8 },
+    // hybrid orbitals that are not one per sigma bond, lone pair and unpaired electron
+    HybridOrbitals { atom: String, expected: usize, found: usize },
+    // p orbitals that are not one per pi bond
+    POrbitals { atom: String, expected: usize, found: usize },
+    // bond listed on one of its atoms but not on the other
+    AsymmetricBond { atom: String, other: String },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::ElectronCount { expected, found } =>
+                write!(f, "{} electrons are drawn instead of {}", found, expected),
+            Violation::TotalCharge { expected, found } =>
+                write!(f, "formal charges add up to {} instead of the molecule's charge of {}", found, expected),
+            Violation::FormalCharge { atom, expected, found } =>
+                write!(f, "{} has a formal charge of {} instead of {}", atom, found, expected),
+            Violation::ShellExceeded { atom, electrons, limit } =>
+                write!(f, "{} has {} electrons, more than the {} its shell holds", atom, electrons, limit),
+            Violation::IncompleteOctet { atom, electrons } =>
+                write!(f, "{} has {} electrons instead of an octet", atom, electrons),
+            Violation::UnpairedElectrons { atom, lone, unpaired } =>
+                write!(f, "{} has {} nonbonding electrons of which {} are unpaired", atom, lone, unpaired),
+            Violation::OrbitalOverfilled { atom, electrons } =>
+                write!(f, "{} has an orbital with {} electrons", atom, electrons),
+            Violation::HybridOrbitals { atom, expected, found } =>
+                write!(f, "{} has {} hybrid orbitals for {} sigma bonds, lone pairs and unpaired electrons", atom, found, expected),
+            Violation::POrbitals { atom, expected, found } =>
+                write!(f, "{} has {} p orbitals for {} pi bonds", atom, found, expected),
+            Violation::AsymmetricBond { atom, other } =>
+                write!(f, "{} is bonded to {} but not the other way around", atom, other),
+        }
+    }
+}
+
+impl Violation {
+    // whether the violation breaks the bookkeeping of the model (electrons, formal charges, orbitals, bonds) rather
+    // than a chemical rule such as the octet, so that only a bug can cause it
+    pub fn is_inconsistency(&self) -> bool {
+        !matches!(self, Violation::ShellExceeded { .. } | Violation::IncompleteOctet { .. })
+    }
+}
+
+fn label(atom: &Atom) -> String {
+    format!("{}{}", atom.symbol(), atom.id())
+}
+
+impl Model {
+    // checks the electron count, formal charges, octets, orbitals and bonds of the structure; empty if it is
+    // consistent
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = vec![];
+
+        let valence = self.atoms().iter().map(Atom::valence).sum::<u32>() as i32;
+        let drawn = self.atoms().iter().map(Atom::lone_electrons).sum::<u32>()
+            + self.bonds().iter().map(|bond| 2 * bond.order()).sum::<u32>();
+        if valence - self.charge() != drawn as i32 {
+            violations.push(Violation::ElectronCount { expected: (valence - self.charge()).max(0) as u32, found: drawn });
+        }
+        if self.total_formal_charge() != self.charge() {
+            violations.push(Violation::TotalCharge { expected: self.charge(), found: self.total_formal_charge() });
+        }
+
+        for (i, atom) in self.atoms().iter().enumerate() {
+            let id = AtomId(i);
+            let (mut sigma, mut pi) = (0, 0);
+            for (other, bond) in self.neighbors(id) {
+                sigma += bond.sigma;
+                pi += bond.pi;
+                let listed_back = self.neighbors(other).any(|(back, back_bond)| back == id && back_bond == bond);
+                if (bond.atoms.0 != id && bond.atoms.1 != id) || !listed_back {
+                    violations.push(Violation::AsymmetricBond { atom: label(atom), other: label(self.atom(other)) });
+                }
+            }
+
+            let formal_charge = atom.valence() as i32 - atom.lone_electrons() as i32 - (sigma + pi) as i32;
+            if atom.formal_charge() != formal_charge {
+                violations.push(Violation::FormalCharge { atom: label(atom), expected: formal_charge, found: atom.formal_charge() });
+            }
+
+            let electrons = self.shell_electrons(id);
+            let limit = match atom.atomic_number() {
+                1..=2 => Some(2),
+                3..=10 => Some(8),
+                _ => None,
+            };
+            if let Some(limit) = limit.filter(|&limit| electrons > limit) {
+                violations.push(Violation::ShellExceeded { atom: label(atom), electrons, limit });
+            }
+            // Li, Be and B are stable with fewer than eight, and the odd electron of a radical leaves its atom at seven
+            let octet = 8 - atom.unpaired_electrons().min(1);
+            if (3..=10).contains(&atom.atomic_number()) && atom.valence() >= 4 && electrons < octet {
+                violations.push(Violation::IncompleteOctet { atom: label(atom), electrons });
+            }
+
+            if let Some(&electrons) = atom.spd_orbitals().iter().chain(atom.p_orbitals()).find(|&&e| e > 2) {
+                violations.push(Violation::OrbitalOverfilled { atom: label(atom), electrons });
+            }
+            if atom.unpaired_electrons() > atom.lone_electrons() || (atom.lone_electrons() - atom.unpaired_electrons()) % 2 != 0 {
+                violations.push(Violation::UnpairedElectrons { atom: label(atom), lone: atom.lone_electrons(), unpaired: atom.unpaired_electrons() });
+            }
+            let hybrid = (sigma + atom.lone_pairs() + atom.unpaired_electrons()) as usize;
+            if atom.spd_orbitals().len() != hybrid {
+                violations.push(Violation::HybridOrbitals { atom: label(atom), expected: hybrid, found: atom.spd_orbitals().len() });
+            }
+            if atom.p_orbitals().len() != pi as usize {
+                violations.push(Violation::POrbitals { atom: label(atom), expected: pi as usize, found: atom.p_orbitals().len() });
+            }
+        }
+        violations
+    }
+}
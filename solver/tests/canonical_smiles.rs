@@ -0,0 +1,66 @@
+use lewis_structure_solver::smiles::write_smiles;
+use lewis_structure_solver::{parse, solve, ElementTable, InputFormat, Model};
+
+fn solve_smiles(smiles: &str) -> Model {
+    let table = ElementTable::embedded().unwrap();
+    solve(&parse(smiles, None, InputFormat::Smiles, &table).unwrap()).unwrap()
+}
+
+// canonical SMILES of the model with its atoms stored in the order `order` gives them
+fn write_reordered(model: &Model, order: &[usize]) -> String {
+    let atoms = order.iter().map(|&i| &model.atoms()[i]).collect::<Vec<_>>();
+    let position = |i: usize| order.iter().position(|&j| j == i).unwrap();
+    let bonds = model.bonds().iter().map(|bond| (position(bond.atoms.0.0), position(bond.atoms.1.0), bond.order())).collect::<Vec<_>>();
+    write_smiles(
+        &atoms.iter().map(|atom| atom.symbol().to_string()).collect::<Vec<_>>(),
+        &atoms.iter().map(|atom| atom.formal_charge()).collect::<Vec<_>>(),
+        &atoms.iter().map(|atom| atom.unpaired_electrons() > 0).collect::<Vec<_>>(),
+        &bonds,
+    )
+}
+
+#[test]
+fn different_inputs_give_the_same_string() {
+    for (inputs, canonical) in [
+        (&["OCC", "CCO", "C(O)C"][..], "CCO"),
+        (&["c1ccccc1O", "Oc1ccccc1", "C1=CC=CC(O)=C1"][..], "OC1=CC=CC=C1"),
+        (&["N#C", "C#N"][..], "C#N"),
+        (&["CC(=O)[O-]", "[O-]C(C)=O", "O=C([O-])C"][..], "CC([O-])=O"),
+        (&["C(C1CC1)C1CC1", "C1CC1CC1CC1"][..], "C1CC1CC1CC1"),
+    ] {
+        for input in inputs {
+            assert_eq!(solve_smiles(input).to_smiles(), canonical, "{}", input);
+        }
+    }
+}
+
+// the string does not depend on the order the atoms are stored in
+#[test]
+fn atom_order_does_not_matter() {
+    for input in ["CCO", "c1ccccc1O", "CC(=O)[O-]", "C1CCC2CCCCC2C1", "[NH4+]", "CC(C)(C)O"] {
+        let model = solve_smiles(input);
+        let count = model.atoms().len();
+        let written = write_reordered(&model, &(0..count).collect::<Vec<_>>());
+        assert_eq!(written, model.to_smiles(), "{}", input);
+        assert_eq!(write_reordered(&model, &(0..count).rev().collect::<Vec<_>>()), written, "{} reversed", input);
+        for shift in 1..count {
+            let rotated = (0..count).map(|i| (i + shift) % count).collect::<Vec<_>>();
+            assert_eq!(write_reordered(&model, &rotated), written, "{} rotated by {}", input, shift);
+        }
+    }
+}
+
+#[test]
+fn charges_are_written_in_brackets() {
+    assert_eq!(solve_smiles("[NH4+]").to_smiles(), "[NH4+]");
+    assert_eq!(solve_smiles("[O-][N+](=O)[O-]").to_smiles(), "[O-][N+]([O-])=O");
+    let symbols = ["Fe".to_string(), "O".to_string()];
+    assert_eq!(write_smiles(&symbols, &[2, -2], &[false, false], &[]), "[Fe+2].[O-2]");
+}
+
+// ring digits are freed when their ring closes and used again for the next ring
+#[test]
+fn ring_digits_are_reused() {
+    assert_eq!(solve_smiles("C1CC1C2CC2").to_smiles(), "C1CC1C1CC1");
+    assert_eq!(solve_smiles("C1CCC2CCCCC2C1").to_smiles(), "C1CCC2CCCCC2C1");
+}
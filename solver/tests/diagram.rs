@@ -0,0 +1,46 @@
+use lewis_structure_solver::{parse, solve, ElementTable, InputFormat};
+
+fn ascii(compound: &str, format: InputFormat) -> String {
+    let table = ElementTable::embedded().unwrap();
+    solve(&parse(compound, None, format, &table).unwrap()).unwrap().render_ascii()
+}
+
+#[test]
+fn simple_molecule_diagram() {
+    assert_eq!(ascii("CH2O", InputFormat::Formula), "\
+H
+  \\     ..
+    C ==O
+   /    ˙˙
+H
+");
+}
+
+#[test]
+fn rings_are_closed() {
+    assert_eq!(ascii("c1ccccc1", InputFormat::Smiles), "
+    H           H
+      \\        /
+        C --C
+      //      \\\\
+H --C           C --H
+      \\        /
+        C ==C
+       /      \\
+    H           H
+".trim_start_matches('\n'));
+
+    // every bond is drawn between neighboring atoms, none left to list below the drawing
+    for smiles in ["C1CC1", "C1CCCCC1", "c1ccncc1", "c1ccc2ccccc2c1", "c1ccc2c(c1)ccc1ccccc12", "c1ccccc1-c1ccccc1",
+        "c1cc2ccc3ccc4ccc5ccc6ccc1c1c2c3c4c5c61"] {
+        let diagram = ascii(smiles, InputFormat::Smiles);
+        assert!(!diagram.contains("also bonded"), "{}:\n{}", smiles, diagram);
+    }
+}
+
+// bonds that a flat grid cannot draw, such as the ones closing a ring of more than eight atoms, are listed
+#[test]
+fn undrawn_bonds_are_listed() {
+    let diagram = ascii("C1CCCCCCCCC1", InputFormat::Smiles);
+    assert_eq!(diagram.lines().filter(|line| line.starts_with("also bonded: ")).count(), 1, "{}", diagram);
+}
@@ -0,0 +1,27 @@
+use lewis_structure_solver::{parse, solve, AtomId, ElementTable, InputFormat};
+
+// group 2 and 13 central atoms keep a formal charge of zero and less than an octet instead of taking pi bonds
+#[test]
+fn group_2_and_13_central_atoms_stay_electron_deficient() {
+    let table = ElementTable::embedded().unwrap();
+    for (compound, central, electrons) in [("BF3", "B", 6), ("BCl3", "B", 6), ("BH3", "B", 6), ("AlCl3", "Al", 6),
+        ("GaCl3", "Ga", 6), ("BeCl2", "Be", 4), ("BeH2", "Be", 4)] {
+        let model = solve(&parse(compound, None, InputFormat::Formula, &table).unwrap()).unwrap();
+        assert!(model.bonds().iter().all(|bond| bond.pi == 0), "{}", compound);
+        assert!(model.atoms().iter().all(|atom| atom.formal_charge() == 0), "{}", compound);
+        let deficient = model.electron_deficient();
+        assert_eq!(deficient.len(), 1, "{}", compound);
+        assert_eq!(model.atom(deficient[0]).symbol(), central, "{}", compound);
+        assert_eq!(model.shell_electrons(deficient[0]), electrons, "{}", compound);
+        assert_eq!(model.validate(), vec![], "{}", compound);
+    }
+}
+
+#[test]
+fn full_octets_are_not_electron_deficient() {
+    let table = ElementTable::embedded().unwrap();
+    for (compound, charge) in [("BF4", Some(-1)), ("BH4", Some(-1)), ("CH4", None), ("NO3", Some(-1)), ("SF6", None)] {
+        let model = solve(&parse(compound, charge, InputFormat::Formula, &table).unwrap()).unwrap();
+        assert_eq!(model.electron_deficient(), Vec::<AtomId>::new(), "{}", compound);
+    }
+}
@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+use std::process::Command;
+use lewis_structure_solver::{ElementTable, LewisError};
+
+#[test]
+fn elements_are_found_by_symbol() {
+    let table = ElementTable::embedded().unwrap();
+    let oxygen = table.by_symbol("O").unwrap();
+    assert_eq!((oxygen.symbol(), oxygen.atomic_number(), oxygen.element_name()), ("O", 8, "Oxygen"));
+    assert_eq!(table.by_symbol("Cl").unwrap().atomic_number(), 17);
+    // symbols are looked up as written in a formula, padded or not
+    assert_eq!(table.by_symbol("O ").unwrap().atomic_number(), 8);
+    assert_eq!(table.by_symbol("Og").unwrap().atomic_number(), 118);
+}
+
+#[test]
+fn unknown_symbols_are_not_found() {
+    let table = ElementTable::embedded().unwrap();
+    for symbol in ["Xx", "", "o", "CL", "Oxygen"] {
+        assert!(table.by_symbol(symbol).is_none(), "{}", symbol);
+    }
+}
+
+#[test]
+fn elements_are_found_by_atomic_number() {
+    let table = ElementTable::embedded().unwrap();
+    assert_eq!(table.by_atomic_number(1).unwrap().symbol(), "H");
+    assert_eq!(table.by_atomic_number(6).unwrap().symbol(), "C");
+    assert_eq!(table.by_atomic_number(54).unwrap().symbol(), "Xe");
+    for atomic_number in 1..=118 {
+        let element = table.by_atomic_number(atomic_number).unwrap();
+        assert_eq!(table.by_symbol(element.symbol()), Some(element));
+    }
+    assert!(table.by_atomic_number(0).is_none());
+    assert!(table.by_atomic_number(119).is_none());
+}
+
+// the data.csv columns, for tables written in the tests
+const HEADER: &str = "atomicNumber, symbol, name, atomicMass, cpkHexColor, electronicConfiguration, electronegativity, \
+    atomicRadius, ionRadius, vanDerWaalsRadius, ionizationEnergy, electronAffinity, oxidationStates, standardState, \
+    bondingType, meltingPoint, boilingPoint,density, groupBlock, yearDiscovered";
+
+fn table_of(rows: &[&str]) -> ElementTable {
+    ElementTable::from_reader(format!("{}\n{}\n", HEADER, rows.join("\n")).as_bytes()).unwrap()
+}
+
+#[test]
+fn masses_are_read_without_uncertainty_or_brackets() {
+    let table = table_of(&[
+        "1, H , Hydrogen, 1.00794(4),FFFFFF,1s1 ,2.2,37,,120,1312,-73,\"-1, 1\",gas,diatomic,14,20,0.0000899,nonmetal,1766",
+        "84, Po , Polonium, [209],AB5C00,[Xe] 4f14 5d10 6s2 6p4 ,2,,94 (+4),,812,-183,\"-2, 2, 4, 6\",solid,metallic,527,1235,9.196,metalloid,1898",
+    ]);
+    assert_eq!(table.by_symbol("H").unwrap().atomic_mass(), 1.00794);
+    assert_eq!(table.by_symbol("Po").unwrap().atomic_mass(), 209.0);
+    assert_eq!(ElementTable::embedded().unwrap().by_symbol("Po").unwrap().atomic_mass(), 209.0);
+}
+
+#[test]
+fn ion_radii_are_read_with_their_charge() {
+    let table = table_of(&[
+        "3, Li , Lithium, 6.941(2),CC80FF,[He] 2s1 ,0.98,134,76 (+1),182,520,-60,1,solid,metallic,454,1615,0.535,alkali metal,1817",
+        "24, Cr , Chromium, 51.9961(6),8A99C7,[Ar] 3d5 4s1 ,1.66,127,80 (+2*),,653,-64,\"-2, -1, 1, 2, 3, 4, 5, 6\",solid,metallic,2180,2944,7.14,transition metal,Ancient",
+        "8, O , Oxygen, 15.9994(3),FF0D0D,[He] 2s2 2p4 ,3.44,73,140 (-2),152,1314,-141,\"-2, -1, 1, 2\",gas,diatomic,55,90,0.001429,nonmetal,1774",
+        "2, He , Helium, 4.002602(2),D9FFFF,1s2 ,,32,,140,2372,0,,gas,atomic,,4,0.0001785,noble gas,1868",
+    ]);
+    let ion_radius = |symbol: &str| table.by_symbol(symbol).unwrap().ion_radius().map(|ion| (ion.radius, ion.charge));
+    assert_eq!(ion_radius("Li"), Some((76.0, 1)));
+    assert_eq!(ion_radius("Cr"), Some((80.0, 2)));
+    assert_eq!(ion_radius("O"), Some((140.0, -2)));
+    assert_eq!(ion_radius("He"), None);
+}
+
+#[test]
+fn cpk_colors_are_read_as_rgb() {
+    let table = table_of(&[
+        "8, O , Oxygen, 15.9994(3),FF0D0D,[He] 2s2 2p4 ,3.44,73,140 (-2),152,1314,-141,\"-2, -1, 1, 2\",gas,diatomic,55,90,0.001429,nonmetal,1774",
+        "6, C , Carbon, 12.0107(8),909090,[He] 2s2 2p2 ,2.55,77,16 (+4),170,1086,-154,\"-4, -3, -2, -1, 1, 2, 3, 4\",solid,covalent network,3823,4300,2.267,nonmetal,Ancient",
+        // a color a spreadsheet turned into a number
+        "9, F , Fluorine, 18.9984032(5),9.00E+51,[He] 2s2 2p5 ,3.98,71,133 (-1),147,1681,-328,-1,gas,diatomic,54,85,0.001696,halogen,1670",
+    ]);
+    assert_eq!(table.by_symbol("O").unwrap().cpk_color(), Some([0xFF, 0x0D, 0x0D]));
+    assert_eq!(table.by_symbol("C").unwrap().cpk_color(), Some([0x90, 0x90, 0x90]));
+    assert_eq!(table.by_symbol("F").unwrap().cpk_color(), None);
+}
+
+// a copy of the built-in table with oxygen's covalent radius changed, in a scratch directory with an out/ directory
+fn scratch_table(name: &str, edit: impl Fn(&str) -> String) -> (PathBuf, String) {
+    let dir = std::env::temp_dir().join(format!("lewis-elements-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(dir.join("out")).unwrap();
+    let csv = include_str!("../data/data.csv").lines().map(|line| edit(line) + "\n").collect::<String>();
+    let path = dir.join("data.csv");
+    std::fs::write(&path, csv).unwrap();
+    (dir, path.display().to_string())
+}
+
+fn larger_oxygen(line: &str) -> String {
+    if line.starts_with("8, O ,") { line.replace(",3.44,73,", ",3.44,100,") } else { line.to_string() }
+}
+
+// O-H bond length in the .xyz file the command line writes for water
+fn water_bond_length(dir: &PathBuf, args: &[&str], env: Option<&str>) -> f64 {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_lewis-structure-solver"));
+    command.args(["H2O", "--xyz"]).args(args).current_dir(dir).env_remove("LEWIS_DATA");
+    if let Some(path) = env {
+        command.env("LEWIS_DATA", path);
+    }
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let xyz = std::fs::read_to_string(dir.join("out/H2O_0.xyz")).unwrap();
+    let position = |symbol: &str| xyz.lines().skip(2).find(|line| line.starts_with(symbol)).unwrap()
+        .split_whitespace().skip(1).map(|x| x.parse::<f64>().unwrap()).collect::<Vec<_>>();
+    let (oxygen, hydrogen) = (position("O"), position("H"));
+    oxygen.iter().zip(&hydrogen).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt()
+}
+
+#[test]
+fn a_table_read_from_a_file_is_used() {
+    let (dir, path) = scratch_table("read", larger_oxygen);
+    let table = ElementTable::read_csv(&path).unwrap();
+    assert_eq!(table.by_symbol("O").unwrap().atomic_radius(), Some(100.0));
+
+    // H is 37 pm, O 73 pm in the built-in table and 100 pm in this one
+    assert!((water_bond_length(&dir, &[], None) - 1.10).abs() < 1e-6);
+    assert!((water_bond_length(&dir, &["--data", &path], None) - 1.37).abs() < 1e-6);
+    assert!((water_bond_length(&dir, &[], Some(&path)) - 1.37).abs() < 1e-6);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn malformed_tables_are_reported_with_their_line() {
+    let (dir, path) = scratch_table("malformed", |line| {
+        if line.starts_with("8, O ,") { line.replace("15.9994(3)", "sixteen") } else { line.to_string() }
+    });
+    let error = ElementTable::read_csv(&path).unwrap_err();
+    assert!(matches!(&error, LewisError::BadElementData(message) if message == "line 9: cannot read atomic mass of O"), "{}", error);
+
+    // a row with a column missing is reported by the csv reader, also with its line
+    std::fs::write(&path, format!("{}\n1, H , Hydrogen, 1.00794(4)\n", HEADER)).unwrap();
+    let error = ElementTable::read_csv(&path).unwrap_err();
+    assert!(matches!(error, LewisError::Csv(_)) && error.to_string().contains("line: 2"), "{}", error);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lewis-structure-solver")).args(["H2O", "--data", &path]).current_dir(&dir)
+        .output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("line: 2"));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(matches!(ElementTable::read_csv("no/such/table.csv"), Err(LewisError::BadElementData(_))));
+}
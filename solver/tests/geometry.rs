@@ -0,0 +1,39 @@
+use lewis_structure_solver::{parse, solve, ElementTable, Geometry, InputFormat};
+use serde_json::Value;
+
+// VSEPR shape of the central atom of each molecule, with the arrangement of its electron domains and AXE notation
+#[test]
+fn central_atoms_get_their_vsepr_shape() {
+    let table = ElementTable::embedded().unwrap();
+    let expected = [
+        ("CH4", "C", Geometry::Tetrahedral, Geometry::Tetrahedral, "AX4"),
+        ("NH3", "N", Geometry::TrigonalPyramidal, Geometry::Tetrahedral, "AX3E1"),
+        ("H2O", "O", Geometry::Bent2Lone, Geometry::Tetrahedral, "AX2E2"),
+        ("SF4", "S", Geometry::Seesaw, Geometry::TrigonalBipyramidal, "AX4E1"),
+        ("ClF3", "Cl", Geometry::TShape, Geometry::TrigonalBipyramidal, "AX3E2"),
+        ("XeF4", "Xe", Geometry::SquarePlanar, Geometry::Octahedral, "AX4E2"),
+        ("BeCl2", "Be", Geometry::Linear, Geometry::Linear, "AX2"),
+    ];
+    for (compound, central, molecular, electron, axe) in expected {
+        let model = solve(&parse(compound, None, InputFormat::Formula, &table).unwrap()).unwrap();
+        assert_eq!(model.geometry(), Some(molecular), "{}", compound);
+
+        let json: Value = serde_json::from_str(&model.to_json(&[])).unwrap();
+        let atom = json["atoms"].as_array().unwrap().iter().find(|atom| atom["name"].as_str().unwrap().trim() == central).unwrap();
+        assert_eq!(atom["molecular_geometry"], serde_json::to_value(molecular).unwrap(), "{}", compound);
+        assert_eq!(atom["electron_geometry"], serde_json::to_value(electron).unwrap(), "{}", compound);
+        assert_eq!(atom["axe"], axe, "{}", compound);
+    }
+}
+
+// terminal atoms count their lone pairs too, but have a single bonded neighbor
+#[test]
+fn terminal_atoms_are_linear() {
+    let table = ElementTable::embedded().unwrap();
+    let model = solve(&parse("HF", None, InputFormat::Formula, &table).unwrap()).unwrap();
+    let json: Value = serde_json::from_str(&model.to_json(&[])).unwrap();
+    let fluorine = json["atoms"].as_array().unwrap().iter().find(|atom| atom["name"] == "F ").unwrap();
+    assert_eq!(fluorine["axe"], "AX1E3");
+    assert_eq!(fluorine["electron_geometry"], "Tetrahedral");
+    assert_eq!(fluorine["molecular_geometry"], "Linear2");
+}
@@ -0,0 +1,158 @@
+// the json written by the solver is read by the display, which is built and released on its own; these tests pin the
+// format to the published schema so that either side can change as long as both keep to it
+use std::fs;
+use std::path::Path;
+use lewis_structure_solver::{json_schema, parse, solve_resonance, ElementTable, InputFormat, LewisError, Model, SCHEMA_VERSION};
+use serde_json::Value;
+
+const PUBLISHED_SCHEMA: &str = include_str!("../../schema/model.schema.json");
+
+fn published_schema() -> Value {
+    serde_json::from_str(PUBLISHED_SCHEMA).expect("schema/model.schema.json is not json")
+}
+
+fn solve_to_json(compound: &str, charge: Option<i32>, format: InputFormat) -> Value {
+    let table = ElementTable::embedded().unwrap();
+    let models = solve_resonance(&parse(compound, charge, format, &table).unwrap()).unwrap();
+    serde_json::from_str(&models[0].to_json(&models)).unwrap()
+}
+
+fn assert_valid(schema: &Value, instance: &Value, what: &str) {
+    let validator = jsonschema::validator_for(schema).unwrap();
+    let errors = validator.iter_errors(instance).map(|e| format!("{} at {}", e, e.instance_path)).collect::<Vec<_>>();
+    assert!(errors.is_empty(), "{} does not match the schema:\n{}", what, errors.join("\n"));
+}
+
+fn outputs() -> Vec<(&'static str, Value)> {
+    vec![
+        ("H2O", solve_to_json("H2O", None, InputFormat::Formula)),
+        ("SO4 2-", solve_to_json("SO4", Some(-2), InputFormat::Formula)),
+        ("NO3-", solve_to_json("NO3", Some(-1), InputFormat::Formula)),
+        ("XeF4", solve_to_json("XeF4", None, InputFormat::Formula)),
+        ("CH3COOH", solve_to_json("CH3COOH", None, InputFormat::Condensed)),
+        ("benzene", solve_to_json("c1ccccc1", None, InputFormat::Smiles)),
+    ]
+}
+
+#[test]
+fn published_schema_is_up_to_date() {
+    // regenerate with `lewis-structure-solver --json-schema > schema/model.schema.json`
+    assert_eq!(PUBLISHED_SCHEMA, json_schema(), "schema/model.schema.json is out of date");
+}
+
+#[test]
+fn output_matches_published_schema() {
+    let schema = published_schema();
+    for (name, output) in outputs() {
+        assert_valid(&schema, &output, name);
+        assert_eq!(output["schema_version"], SCHEMA_VERSION, "{}", name);
+    }
+}
+
+// files written by earlier builds with the same schema_version have to keep validating, otherwise the version needs
+// to be raised and the old fixtures moved aside
+#[test]
+fn fixtures_match_published_schema() {
+    let schema = published_schema();
+    let mut count = 0;
+    for (path, json) in fixtures() {
+        let fixture: Value = serde_json::from_str(&json).unwrap();
+        assert_valid(&schema, &fixture, &path);
+        assert_eq!(fixture["schema_version"], SCHEMA_VERSION, "{}", path);
+        count += 1;
+    }
+    assert!(count > 0, "no fixtures");
+}
+
+fn fixtures() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(format!("v{}", SCHEMA_VERSION));
+    fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .map(|path| (path.display().to_string(), fs::read_to_string(&path).unwrap()))
+        .collect()
+}
+
+// a model read back is written out the same apart from the averaged bond orders, which it does not keep, and
+// fields added to the format after the fixture was written
+#[test]
+fn fixtures_read_back() {
+    let table = ElementTable::embedded().unwrap();
+    for (path, json) in fixtures() {
+        let model = Model::from_json(&json, &table).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        let mut written: Value = serde_json::from_str(&json).unwrap();
+        let mut rewritten: Value = serde_json::from_str(&model.to_json(&[])).unwrap();
+        for bonds in [&mut written["bonds"], &mut rewritten["bonds"]] {
+            for bond in bonds.as_array_mut().unwrap() {
+                bond.as_object_mut().unwrap().remove("order");
+            }
+        }
+        for (atom, written_atom) in rewritten["atoms"].as_array_mut().unwrap().iter_mut().zip(written["atoms"].as_array().unwrap()) {
+            atom.as_object_mut().unwrap().retain(|field, _| written_atom.get(field).is_some());
+        }
+        rewritten.as_object_mut().unwrap().retain(|field, _| written.get(field).is_some());
+        assert_eq!(written, rewritten, "{}", path);
+    }
+}
+
+#[test]
+fn broken_models_are_rejected() {
+    let table = ElementTable::embedded().unwrap();
+    let water = solve_to_json("H2O", None, InputFormat::Formula);
+    let breakages: [fn(&mut Value); 8] = [
+        // written before schema_version
+        |json| { json.as_object_mut().unwrap().remove("schema_version"); },
+        |json| json["schema_version"] = 99.into(),
+        // bonds_with pointing at an atom that does not exist
+        |json| json["atoms"][0]["bonds_with"][0]["id"] = 7.into(),
+        // bond to an atom past the end
+        |json| json["bonds"][0]["atoms"][0] = 9.into(),
+        // second hydrogen renamed to the first
+        |json| json["atoms"][1]["id"] = 0.into(),
+        // formal charge that does not add up
+        |json| json["atoms"][0]["formal_charge"] = 1.into(),
+        // more hybrid orbitals than the oxygen's bonds and lone pairs
+        |json| json["atoms"][2]["spd_orbitals"] = serde_json::json!([2, 2, 2, 2, 2]),
+        // a lone pair without its orbital
+        |json| json["atoms"][2]["spd_orbitals"] = serde_json::json!([2, 2, 2]),
+    ];
+    assert!(Model::from_json(&water.to_string(), &table).is_ok());
+    for (i, breakage) in breakages.iter().enumerate() {
+        let mut json = water.clone();
+        breakage(&mut json);
+        assert!(matches!(Model::from_json(&json.to_string(), &table), Err(LewisError::BadJson(_))), "breakage {}", i);
+    }
+}
+
+// more domains than any hybridization has, with lone pairs to match, which --xyz has no directions for
+#[test]
+fn too_many_hybrid_orbitals_are_rejected() {
+    let table = ElementTable::embedded().unwrap();
+    let mut json = solve_to_json("H2O", None, InputFormat::Formula);
+    let oxygen = &mut json["atoms"][2];
+    oxygen["lone"] = 20.into();
+    oxygen["formal_charge"] = (-16).into();
+    oxygen["spd_orbitals"] = serde_json::json!(vec![2; 12]);
+    assert!(matches!(Model::from_json(&json.to_string(), &table),
+        Err(LewisError::BadJson(message)) if message == "O0 has 12 hybrid orbitals, more than the 9 of sp3d5"));
+}
+
+// the fields and strings display/src/molecule.cpp reads
+#[test]
+fn output_has_what_the_display_reads() {
+    let hybridizations = ["S", "SP", "SP2", "SP3", "SP3D", "SP3D2", "SP3D3", "SP3D4", "SP3D5"];
+    for (name, output) in outputs() {
+        let atoms = output["atoms"].as_array().unwrap();
+        for atom in atoms {
+            assert!(atom["id"].is_u64() && atom["lone"].is_u64(), "{}: {}", name, atom);
+            assert!(atom["p_orbitals"].is_array(), "{}: {}", name, atom);
+            assert!(hybridizations.contains(&atom["hybridization"].as_str().unwrap()), "{}: {}", name, atom);
+            let symbol = atom["name"].as_str().unwrap();
+            assert_eq!(symbol.len(), 2, "{}: names are padded to two characters", name);
+            for bond in atom["bonds_with"].as_array().unwrap() {
+                assert!(["SIGMA", "PI"].contains(&bond["bond_type"].as_str().unwrap()), "{}: {}", name, bond);
+                // every bond points at an atom that exists
+                assert!(atoms.iter().any(|other| other["name"] == bond["name"] && other["id"] == bond["id"]), "{}: {}", name, bond);
+            }
+        }
+    }
+}
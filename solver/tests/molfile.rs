@@ -0,0 +1,72 @@
+use lewis_structure_solver::{parse, solve, ElementTable, InputFormat, MolfileVersion};
+
+fn molfile(compound: &str, charge: Option<i32>, version: MolfileVersion) -> String {
+    let table = ElementTable::embedded().unwrap();
+    solve(&parse(compound, charge, InputFormat::Formula, &table).unwrap()).unwrap().to_molfile(version)
+}
+
+// the columns of a V2000 line from `start` to `end`, counted from 1 as in the CTfile specification
+fn columns(line: &str, start: usize, end: usize) -> &str {
+    line[start - 1..end].trim()
+}
+
+#[test]
+fn v2000_blocks_have_fixed_columns() {
+    let block = molfile("NO3", Some(-1), MolfileVersion::V2000);
+    let lines = block.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "NO3_-1");
+    let counts = lines[3];
+    assert_eq!((columns(counts, 1, 3), columns(counts, 4, 6)), ("4", "3"));
+    assert_eq!(columns(counts, 35, 39), "V2000");
+
+    let atoms = &lines[4..8];
+    let symbols = atoms.iter().map(|line| columns(line, 32, 34)).collect::<Vec<_>>();
+    assert_eq!(symbols, ["N", "O", "O", "O"]);
+    for line in atoms {
+        for (start, end) in [(1, 10), (11, 20), (21, 30)] {
+            assert!(columns(line, start, end).parse::<f64>().is_ok(), "{}", line);
+        }
+    }
+    // charge codes: 3 is +1, 5 is -1
+    let charges = atoms.iter().map(|line| columns(line, 37, 39)).collect::<Vec<_>>();
+    assert_eq!(charges, ["3", "5", "5", "0"]);
+
+    let bonds = lines[8..11].iter().map(|line| [(1, 3), (4, 6), (7, 9)].map(|(start, end)| columns(line, start, end)))
+        .collect::<Vec<_>>();
+    assert_eq!(bonds, [["1", "2", "1"], ["1", "3", "1"], ["1", "4", "2"]]);
+
+    // the charges are written again as a property, which replaces the atom block codes
+    assert_eq!(&lines[11..], ["M  CHG  3   1   1   2  -1   3  -1", "M  END"]);
+}
+
+#[test]
+fn uncharged_molecules_have_no_charge_lines() {
+    let block = molfile("H2O", None, MolfileVersion::V2000);
+    assert!(!block.contains("M  CHG"));
+    assert!(block.ends_with("M  END\n"));
+}
+
+#[test]
+fn v3000_blocks_are_a_ctab() {
+    assert_eq!(molfile("NO3", Some(-1), MolfileVersion::V3000), "\
+NO3_-1
+  lewis-structure-solver     2D
+
+  0  0  0     0  0            999 V3000
+M  V30 BEGIN CTAB
+M  V30 COUNTS 4 3 0 0 0
+M  V30 BEGIN ATOM
+M  V30 1 N 0.0000 0.0000 0.0000 0 CHG=1
+M  V30 2 O 1.5000 0.0000 0.0000 0 CHG=-1
+M  V30 3 O -0.7500 1.2990 0.0000 0 CHG=-1
+M  V30 4 O -0.7500 -1.2990 0.0000 0
+M  V30 END ATOM
+M  V30 BEGIN BOND
+M  V30 1 1 1 2
+M  V30 2 1 1 3
+M  V30 3 2 1 4
+M  V30 END BOND
+M  V30 END CTAB
+M  END
+");
+}
@@ -0,0 +1,38 @@
+use lewis_structure_solver::{parse, solve, solve_resonance, ElementTable, InputFormat, MolfileVersion};
+use serde_json::Value;
+
+// the odd electron goes where the formal charges come out best, on the least electronegative atom on a tie
+#[test]
+fn odd_electron_is_unpaired_on_one_atom() {
+    let table = ElementTable::embedded().unwrap();
+    for (compound, charge, radical, smiles) in [("NO", None, "N", "[N]=O"), ("ClO2", None, "Cl", "O=[Cl]=O"),
+        ("CH3", None, "C", "[CH3]"), ("OH", None, "O", "[OH]"), ("NO2", None, "O", "O=N[O]"), ("O2", Some(-1), "O", "[O-][O]")] {
+        let model = solve(&parse(compound, charge, InputFormat::Formula, &table).unwrap()).unwrap();
+        let radicals = model.radicals();
+        assert_eq!(radicals.len(), 1, "{}", compound);
+        let atom = model.atom(radicals[0]);
+        assert_eq!((atom.symbol(), atom.unpaired_electrons()), (radical, 1), "{}", compound);
+        assert_eq!(atom.spd_orbitals().iter().filter(|&&electrons| electrons == 1).count(), 1, "{}", compound);
+        assert_eq!(model.total_formal_charge(), charge.unwrap_or(0), "{}", compound);
+        assert_eq!(model.validate(), vec![], "{}", compound);
+        assert_eq!(model.to_smiles(), smiles, "{}", compound);
+    }
+}
+
+#[test]
+fn radicals_are_flagged_in_the_output() {
+    let table = ElementTable::embedded().unwrap();
+    let models = solve_resonance(&parse("NO2", None, InputFormat::Formula, &table).unwrap()).unwrap();
+    assert_eq!(models.len(), 2);
+    let json: Value = serde_json::from_str(&models[0].to_json(&models)).unwrap();
+    assert_eq!(json["radical"], true);
+    let unpaired = json["atoms"].as_array().unwrap().iter().map(|atom| atom["unpaired"].as_u64().unwrap()).collect::<Vec<_>>();
+    assert_eq!(unpaired.iter().sum::<u64>(), 1);
+    assert!(models[0].to_molfile(MolfileVersion::V2000).contains("M  RAD  1"));
+    assert!(models[0].to_molfile(MolfileVersion::V3000).contains(" RAD=2"));
+    // a single dot next to the oxygen with the odd electron
+    assert!(models[0].render_ascii().contains("·O") || models[0].render_ascii().contains("O·"));
+
+    let water: Value = serde_json::from_str(&solve(&parse("H2O", None, InputFormat::Formula, &table).unwrap()).unwrap().to_json(&[])).unwrap();
+    assert_eq!(water["radical"], false);
+}
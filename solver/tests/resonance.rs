@@ -0,0 +1,60 @@
+use std::process::Command;
+use lewis_structure_solver::{parse, solve_resonance, ElementTable, InputFormat, Model};
+
+fn resonance_models(compound: &str, charge: Option<i32>, format: InputFormat) -> Vec<Model> {
+    let table = ElementTable::embedded().unwrap();
+    solve_resonance(&parse(compound, charge, format, &table).unwrap()).unwrap()
+}
+
+// pi bonds of every bond, which tell resonance structures apart even where their canonical SMILES are the same
+fn pi_bonds(model: &Model) -> Vec<u32> {
+    model.bonds().iter().map(|bond| bond.pi).collect()
+}
+
+// symmetry-equivalent structures are resonance structures of their own, and none is listed twice
+#[test]
+fn resonance_structures_are_counted_once() {
+    for (compound, charge, format, count) in [("CO3", Some(-2), InputFormat::Formula, 3), ("NO3", Some(-1), InputFormat::Formula, 3),
+        ("c1ccccc1", None, InputFormat::Smiles, 2), ("O3", None, InputFormat::Formula, 2),
+        ("c1ccc2cc3ccccc3cc2c1", None, InputFormat::Smiles, 4), ("H2O", None, InputFormat::Formula, 1)] {
+        let models = resonance_models(compound, charge, format);
+        assert_eq!(models.len(), count, "{}", compound);
+        for (i, model) in models.iter().enumerate() {
+            assert!(models[i + 1..].iter().all(|other| pi_bonds(other) != pi_bonds(model)), "{} lists a structure twice", compound);
+            assert_eq!(model.validate(), vec![], "{}", compound);
+        }
+    }
+}
+
+// the command line in a scratch directory, where it writes its out/ files
+fn run(args: &[&str]) -> (bool, String, String) {
+    let dir = std::env::temp_dir().join(format!("lewis-resonance-{}-{}", std::process::id(), args.join("_")));
+    std::fs::create_dir_all(dir.join("out")).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_lewis-structure-solver")).args(args).current_dir(&dir).output().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    (output.status.success(), String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn resonance_flag_prints_every_structure() {
+    let (success, stdout, stderr) = run(&["CO3", "-2", "--resonance", "--check"]);
+    assert!(success, "{}", stderr);
+    let headings = stdout.lines().filter(|line| line.starts_with("Resonance structure")).collect::<Vec<_>>();
+    assert_eq!(headings, ["Resonance structure 1 of 3:", "Resonance structure 2 of 3:", "Resonance structure 3 of 3:"]);
+    assert_eq!(stdout.lines().filter(|&line| line == "SMILES: [O-]C([O-])=O").count(), 3);
+    assert!(stdout.lines().any(|line| line == "Check: ok"), "{}", stdout);
+
+    // without --resonance only the first one is printed
+    let (success, stdout, _) = run(&["CO3", "-2"]);
+    assert!(success);
+    assert!(!stdout.contains("Resonance structure"));
+    assert_eq!(stdout.lines().filter(|line| line.starts_with("SMILES:")).count(), 1);
+}
+
+#[test]
+fn check_flag_reports_violations() {
+    let (success, stdout, stderr) = run(&["CH2", "--check"]);
+    assert!(!success);
+    assert!(!stdout.contains("Check: ok"));
+    assert_eq!(stderr.trim(), "check failed: C0 has 6 electrons instead of an octet");
+}
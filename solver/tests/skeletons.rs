@@ -0,0 +1,79 @@
+use lewis_structure_solver::{parse, solve, AtomId, ElementTable, InputFormat, LewisError, Model};
+
+fn solve_formula(compound: &str) -> Model {
+    let table = ElementTable::embedded().unwrap();
+    solve(&parse(compound, None, InputFormat::Formula, &table).unwrap()).unwrap()
+}
+
+fn symbol(model: &Model, id: AtomId) -> &str {
+    model.atom(id).symbol()
+}
+
+// oxyacids have every oxygen on the central atom and every hydrogen on an oxygen, without O-O bonds
+#[test]
+fn oxyacids_have_a_central_atom() {
+    for (compound, central, oxygens) in [("H2SO4", "S", 4), ("H3PO4", "P", 4), ("HClO4", "Cl", 4), ("HNO3", "N", 3)] {
+        let model = solve_formula(compound);
+        let center = (0..model.atoms().len()).map(AtomId).find(|&id| symbol(&model, id) == central).unwrap();
+        assert_eq!(model.neighbors(center).filter(|&(other, _)| symbol(&model, other) == "O").count(), oxygens, "{}", compound);
+        for bond in model.bonds() {
+            let pair = [symbol(&model, bond.atoms.0), symbol(&model, bond.atoms.1)];
+            assert_ne!(pair, ["O", "O"], "{}", compound);
+            if pair.contains(&"H") {
+                assert!(pair.contains(&"O"), "{}: hydrogen bonded to {:?}", compound, pair);
+            }
+        }
+        assert_eq!(model.validate(), vec![], "{}", compound);
+    }
+}
+
+// oxygen still chains when nothing less electronegative can take its place
+#[test]
+fn peroxides_keep_their_chain() {
+    for (compound, smiles) in [("H2O2", "OO"), ("O2F2", "FOOF"), ("O3", "[O-][O+]=O")] {
+        assert_eq!(solve_formula(compound).to_smiles(), smiles, "{}", compound);
+    }
+}
+
+// hydrogens left out of --bonds go where the solver would put them
+#[test]
+fn given_bonds_get_their_hydrogens() {
+    let table = ElementTable::embedded().unwrap();
+    for (bonds, smiles) in [("C0-C1,C1-O0", "CCO"), ("C0-O0,C1-O0", "COC"), ("C0-O0,C1-O0,C0-H0,C0-H1,C0-H2,C1-H3,C1-H4,C1-H5", "COC")] {
+        let molecule = parse("C2H6O", None, InputFormat::Formula, &table).unwrap().with_bonds(bonds).unwrap();
+        let model = solve(&molecule).unwrap();
+        assert_eq!(model.to_smiles(), smiles, "{}", bonds);
+        assert_eq!(model.bonds().len(), 8, "{}", bonds);
+    }
+}
+
+// hydrates, salts and metal complexes are not forced into one covalent structure
+#[test]
+fn several_molecules_and_ionic_compounds_have_no_structure() {
+    let table = ElementTable::embedded().unwrap();
+    for (compound, format) in [("CuSO4·5H2O", InputFormat::Formula), ("NaCl", InputFormat::Formula), ("CuSO4", InputFormat::Formula),
+        ("MgCl2", InputFormat::Formula), ("[NH4+].[Cl-]", InputFormat::Smiles)] {
+        let result = solve(&parse(compound, None, format, &table).unwrap());
+        assert!(matches!(result, Err(LewisError::NoValidStructure(_))), "{}", compound);
+    }
+    for compound in ["BeCl2", "AlCl3", "Fe++"] {
+        assert!(solve(&parse(compound, None, InputFormat::Formula, &table).unwrap()).is_ok(), "{}", compound);
+    }
+}
+
+// formulas only give chains; rings have to come from SMILES or --bonds
+#[test]
+fn formulas_give_chains() {
+    assert_eq!(solve_formula("C6H6").to_smiles(), "C#CC=CC=C");
+}
+
+// long chains have too many bond order assignments to try them all; the search has to find the full octets anyway
+#[test]
+fn polyynes_get_full_octets() {
+    for (compound, triple_bonds) in [("C18H2", 9), ("C22H2", 11)] {
+        let model = solve_formula(compound);
+        assert_eq!(model.validate(), vec![], "{}", compound);
+        assert_eq!(model.bonds().iter().filter(|bond| bond.pi == 2).count(), triple_bonds, "{}", compound);
+        assert!(model.atoms().iter().all(|atom| atom.formal_charge() == 0), "{}", compound);
+    }
+}
@@ -0,0 +1,68 @@
+use lewis_structure_solver::{parse, solve, ElementTable, InputFormat};
+
+fn svg(compound: &str, charge: Option<i32>, wedges: bool) -> String {
+    let table = ElementTable::embedded().unwrap();
+    solve(&parse(compound, charge, InputFormat::Formula, &table).unwrap()).unwrap().to_svg(wedges)
+}
+
+// value of an attribute of an svg element written on one line, e.g. `x` of `<text x="60.0" ...>`
+fn attribute(element: &str, name: &str) -> Option<f64> {
+    let start = element.find(&format!(" {}=\"", name))? + name.len() + 3;
+    element[start..].split('"').next()?.parse().ok()
+}
+
+// text elements as (x, y, text, whether it is the smaller text of a charge)
+fn texts(svg: &str) -> Vec<(f64, f64, String, bool)> {
+    svg.lines().map(str::trim).filter(|line| line.starts_with("<text ")).map(|line| {
+        let text = line[line.find('>').unwrap() + 1..line.rfind("</text>").unwrap()].to_string();
+        (attribute(line, "x").unwrap(), attribute(line, "y").unwrap(), text, line.contains("font-size"))
+    }).collect()
+}
+
+fn lines(svg: &str) -> Vec<[f64; 4]> {
+    svg.lines().map(str::trim).filter(|line| line.starts_with("<line ")).map(|line| {
+        ["x1", "y1", "x2", "y2"].map(|name| attribute(line, name).unwrap())
+    }).collect()
+}
+
+#[test]
+fn atoms_bonds_and_charges_are_drawn() {
+    let image = svg("NO3", Some(-1), false);
+    assert!(image.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(image.contains("<title>NO3_-1</title>"));
+    assert!(image.ends_with("</svg>\n"));
+
+    let texts = texts(&image);
+    let symbols = texts.iter().filter(|text| !text.3).map(|text| text.2.as_str()).collect::<Vec<_>>();
+    assert_eq!(symbols, ["N", "O", "O", "O"]);
+    let mut charges = texts.iter().filter(|text| text.3).map(|text| text.2.as_str()).collect::<Vec<_>>();
+    charges.sort();
+    assert_eq!(charges, ["+", "\u{2212}", "\u{2212}"]);
+
+    // two single bonds and a double one, each line running from near one atom to near the other
+    let lines = lines(&image);
+    assert_eq!(lines.len(), 4);
+    let (nitrogen_x, nitrogen_y) = (texts[0].0, texts[0].1);
+    for [x1, y1, x2, y2] in lines {
+        let from_nitrogen = (x1 - nitrogen_x).hypot(y1 - nitrogen_y).min((x2 - nitrogen_x).hypot(y2 - nitrogen_y));
+        assert!(from_nitrogen < 20.0, "bond line {:?} does not start at the nitrogen", [x1, y1, x2, y2]);
+        let to_oxygen = texts.iter().filter(|text| text.2 == "O")
+            .map(|text| (x1 - text.0).hypot(y1 - text.1).min((x2 - text.0).hypot(y2 - text.1)))
+            .fold(f64::INFINITY, f64::min);
+        assert!(to_oxygen < 20.0, "bond line {:?} does not end at an oxygen", [x1, y1, x2, y2]);
+    }
+
+    // the oxygens' lone pairs, two dots each
+    assert_eq!(image.matches("<circle ").count(), 16);
+}
+
+#[test]
+fn wedges_replace_lines_out_of_the_page() {
+    let flat = svg("CH4", None, false);
+    let perspective = svg("CH4", None, true);
+    assert_eq!(lines(&flat).len(), 4);
+    assert!(!flat.contains("<polygon"));
+    // one wedge towards the viewer, one hashed wedge of six lines away from it
+    assert_eq!(perspective.matches("<polygon ").count(), 1);
+    assert_eq!(lines(&perspective).len(), 2 + 6);
+}
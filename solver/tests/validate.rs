@@ -0,0 +1,67 @@
+use lewis_structure_solver::{parse, solve_resonance, ElementTable, InputFormat, Model, Violation};
+use serde_json::Value;
+
+#[test]
+fn solved_structures_are_valid() {
+    let table = ElementTable::embedded().unwrap();
+    let compounds = [
+        ("H2O", None, InputFormat::Formula), ("SO4", Some(-2), InputFormat::Formula), ("NO3", Some(-1), InputFormat::Formula),
+        ("NH4", Some(1), InputFormat::Formula), ("SF6", None, InputFormat::Formula), ("XeF4", None, InputFormat::Formula),
+        ("I3", Some(-1), InputFormat::Formula), ("HCN", None, InputFormat::Formula), ("O3", None, InputFormat::Formula),
+        ("BF3", None, InputFormat::Formula), ("CH3COOH", None, InputFormat::Condensed),
+        ("CH3CO2H", None, InputFormat::Condensed), ("CH3COCH3", None, InputFormat::Condensed),
+        ("c1ccncc1", None, InputFormat::Smiles), ("CC(=O)[O-]", None, InputFormat::Smiles),
+    ];
+    for (compound, charge, format) in compounds {
+        for model in solve_resonance(&parse(compound, charge, format, &table).unwrap()).unwrap() {
+            assert_eq!(model.validate(), vec![], "{}", compound);
+        }
+    }
+}
+
+#[test]
+fn broken_structures_are_reported() {
+    let table = ElementTable::embedded().unwrap();
+    let models = solve_resonance(&parse("H2O", None, InputFormat::Formula, &table).unwrap()).unwrap();
+    let mut json: Value = serde_json::from_str(&models[0].to_json(&models)).unwrap();
+    // a third lone pair on the oxygen, in an orbital that holds three electrons
+    let oxygen = &mut json["atoms"][2];
+    oxygen["lone"] = 6.into();
+    oxygen["formal_charge"] = (-2).into();
+    oxygen["spd_orbitals"] = serde_json::json!([2, 2, 2, 2, 3]);
+    let model = Model::from_json(&json.to_string(), &table).unwrap();
+    assert_eq!(model.validate(), vec![
+        Violation::ShellExceeded { atom: "O0".to_string(), electrons: 10, limit: 8 },
+        Violation::OrbitalOverfilled { atom: "O0".to_string(), electrons: 3 },
+    ]);
+}
+
+// the best structure for CH2 leaves carbon with six electrons; it is returned and left to validate to report
+#[test]
+fn rule_breaking_structures_are_returned() {
+    let table = ElementTable::embedded().unwrap();
+    let models = solve_resonance(&parse("CH2", None, InputFormat::Formula, &table).unwrap()).unwrap();
+    assert_eq!(models[0].to_smiles(), "[CH2]");
+    assert_eq!(models[0].validate(), vec![Violation::IncompleteOctet { atom: "C0".to_string(), electrons: 6 }]);
+}
+
+// fused rings have far too many bond order assignments to try; their Kekulé structures come from a matching
+#[test]
+fn fused_aromatics_are_kekulized() {
+    let table = ElementTable::embedded().unwrap();
+    for smiles in ["c1ccc2cc3ccccc3cc2c1", "c1cc2ccc3ccc4ccc5ccc6ccc1c1c2c3c4c5c61"] {
+        let models = solve_resonance(&parse(smiles, None, InputFormat::Smiles, &table).unwrap()).unwrap();
+        for model in &models {
+            assert_eq!(model.validate(), vec![], "{}", smiles);
+            assert!(model.atoms().iter().all(|atom| atom.formal_charge() == 0), "{}", smiles);
+        }
+    }
+}
+
+#[test]
+fn octet_violations_are_not_inconsistencies() {
+    assert!(!Violation::IncompleteOctet { atom: "C0".to_string(), electrons: 6 }.is_inconsistency());
+    assert!(!Violation::ShellExceeded { atom: "O0".to_string(), electrons: 10, limit: 8 }.is_inconsistency());
+    assert!(Violation::ElectronCount { expected: 8, found: 10 }.is_inconsistency());
+    assert!(Violation::OrbitalOverfilled { atom: "O0".to_string(), electrons: 3 }.is_inconsistency());
+}
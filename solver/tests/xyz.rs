@@ -0,0 +1,102 @@
+use lewis_structure_solver::{parse, solve, ElementTable, InputFormat};
+
+// the atoms of an .xyz block as symbol and position
+fn read_xyz(compound: &str, format: InputFormat) -> Vec<(String, [f64; 3])> {
+    let table = ElementTable::embedded().unwrap();
+    let xyz = solve(&parse(compound, None, format, &table).unwrap()).unwrap().to_xyz();
+    let mut lines = xyz.lines();
+    let count: usize = lines.next().unwrap().parse().unwrap();
+    // the comment line is the name of the model
+    assert!(lines.next().is_some_and(|name| !name.is_empty()));
+    let atoms = lines.map(|line| {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        assert_eq!(fields.len(), 4, "{}", line);
+        (fields[0].to_string(), [1, 2, 3].map(|i| fields[i].parse::<f64>().unwrap()))
+    }).collect::<Vec<_>>();
+    assert_eq!(atoms.len(), count, "{}", compound);
+    atoms
+}
+
+fn difference(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn length(a: [f64; 3]) -> f64 {
+    a.iter().map(|x| x * x).sum::<f64>().sqrt()
+}
+
+// angle a-center-b in degrees
+fn angle(a: [f64; 3], center: [f64; 3], b: [f64; 3]) -> f64 {
+    let (u, v) = (difference(a, center), difference(b, center));
+    let cos = (u[0] * v[0] + u[1] * v[1] + u[2] * v[2]) / (length(u) * length(v));
+    cos.acos().to_degrees()
+}
+
+// the central atom and the atoms around it
+fn center_and_ligands(atoms: &[(String, [f64; 3])], center: &str) -> ([f64; 3], Vec<[f64; 3]>) {
+    let position = atoms.iter().find(|(symbol, _)| symbol == center).unwrap().1;
+    (position, atoms.iter().filter(|(symbol, _)| symbol != center).map(|&(_, position)| position).collect())
+}
+
+// single bonds are as long as the covalent radii of their atoms add up to
+fn covalent_bond_length(a: &str, b: &str) -> f64 {
+    let table = ElementTable::embedded().unwrap();
+    let radius = |symbol: &str| table.by_symbol(symbol).unwrap().atomic_radius().unwrap().round();
+    (radius(a) + radius(b)) / 100.0
+}
+
+#[test]
+fn methane_is_tetrahedral() {
+    let atoms = read_xyz("CH4", InputFormat::Formula);
+    let (carbon, hydrogens) = center_and_ligands(&atoms, "C");
+    assert_eq!(hydrogens.len(), 4);
+    for (i, &a) in hydrogens.iter().enumerate() {
+        assert!((length(difference(a, carbon)) - covalent_bond_length("C", "H")).abs() < 1e-4);
+        for &b in &hydrogens[i + 1..] {
+            assert!((angle(a, carbon, b) - 109.47).abs() < 0.01, "{}", angle(a, carbon, b));
+        }
+    }
+}
+
+#[test]
+fn water_is_bent() {
+    let atoms = read_xyz("H2O", InputFormat::Formula);
+    let (oxygen, hydrogens) = center_and_ligands(&atoms, "O");
+    for &hydrogen in &hydrogens {
+        assert!((length(difference(hydrogen, oxygen)) - covalent_bond_length("O", "H")).abs() < 1e-4);
+    }
+    // the two lone pairs take tetrahedral directions too
+    assert!((angle(hydrogens[0], oxygen, hydrogens[1]) - 109.47).abs() < 0.01);
+}
+
+#[test]
+fn linear_and_octahedral_molecules() {
+    let atoms = read_xyz("CO2", InputFormat::Formula);
+    let (carbon, oxygens) = center_and_ligands(&atoms, "C");
+    assert!((angle(oxygens[0], carbon, oxygens[1]) - 180.0).abs() < 0.01);
+    // double bonds are shorter than single ones
+    assert!(length(difference(oxygens[0], carbon)) < covalent_bond_length("C", "O"));
+
+    let atoms = read_xyz("SF6", InputFormat::Formula);
+    let (sulfur, fluorines) = center_and_ligands(&atoms, "S");
+    for (i, &a) in fluorines.iter().enumerate() {
+        for &b in &fluorines[i + 1..] {
+            let angle = angle(a, sulfur, b);
+            assert!((angle - 90.0).abs() < 0.01 || (angle - 180.0).abs() < 0.01, "{}", angle);
+        }
+    }
+}
+
+// every atom gets a line of its own, including those of rings and of a chain, and no two share a position
+#[test]
+fn every_atom_is_placed() {
+    for (compound, format) in [("c1ccccc1", InputFormat::Smiles), ("CH3(CH2)4CH3", InputFormat::Condensed),
+        ("XeF4", InputFormat::Formula), ("CH3COOH", InputFormat::Condensed)] {
+        let atoms = read_xyz(compound, format);
+        for (i, (_, a)) in atoms.iter().enumerate() {
+            for (_, b) in &atoms[i + 1..] {
+                assert!(length(difference(*a, *b)) > 0.5, "{} has two atoms at {:?}", compound, a);
+            }
+        }
+    }
+}